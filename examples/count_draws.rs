@@ -0,0 +1,53 @@
+//! Count how many sprites a ROM draws, using a pre-execute hook.
+//!
+//! ```sh
+//! cargo run --example count_draws -- <ROM_FILE> [CYCLES]
+//! ```
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use chip8stuff::chip8::{instructions::Instruction, Chip8};
+
+const DEFAULT_CYCLES: usize = 1000;
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let rom_file = args
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Usage: count_draws <ROM_FILE> [CYCLES]"))?;
+    let cycles = match args.next() {
+        Some(cycles) => cycles.parse()?,
+        None => DEFAULT_CYCLES,
+    };
+
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&rom_file)?;
+
+    let draws = Arc::new(AtomicUsize::new(0));
+    chip8.set_pre_execute_hook(Box::new({
+        let draws = draws.clone();
+        move |_chip8, instruction| {
+            if let Instruction::DrawSprite { .. } = instruction {
+                draws.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }));
+
+    for _ in 0..cycles {
+        if chip8.waiting_for_key.is_some() {
+            break;
+        }
+
+        chip8.step_cycle()?;
+    }
+
+    println!(
+        "{} sprites drawn in {cycles} cycles",
+        draws.load(Ordering::Relaxed)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+//! Run a ROM without opening a window, then print the screen.
+//!
+//! ```sh
+//! cargo run --example run_headless -- <ROM_FILE> [CYCLES] [--until-frame <N>] [--frames <N>]
+//! ```
+
+use chip8stuff::chip8::{
+    self,
+    run::{self, Limits, StopReason},
+    Chip8,
+};
+use clap::Parser;
+
+#[derive(Parser)]
+struct Args {
+    rom_file: String,
+    /// How many instructions to execute at most
+    #[arg(default_value_t = 1000)]
+    cycles: usize,
+    /// Stop as soon as the ROM changed the screen N times, e.g. 1 to see the first frame it draws
+    #[arg(long)]
+    until_frame: Option<u64>,
+    /// Run until N 60 Hz frames passed instead of a number of instructions, e.g. 60 for a second
+    #[arg(long)]
+    frames: Option<u64>,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let mut chip8 = Chip8::new();
+    chip8.load_rom(&args.rom_file)?;
+
+    let limits = Limits {
+        max_cycles: if args.frames.is_some() {
+            usize::MAX
+        } else {
+            args.cycles
+        },
+        until_frame: args.until_frame,
+        frames: args.frames,
+    };
+    let result = run::run(&mut chip8, limits);
+    let cycles = result.cycles;
+    match result.stop_reason {
+        StopReason::Idle => println!("Stopped after {cycles} cycles, the ROM is idling in a loop"),
+        StopReason::WaitingForKey => {
+            println!("Stopped after {cycles} cycles, the ROM is waiting for a key");
+        }
+        StopReason::UntilFrame => println!(
+            "Frame {} was drawn after {cycles} cycles",
+            args.until_frame.unwrap_or_default()
+        ),
+        StopReason::Frames => println!("{} frames passed after {cycles} cycles", result.frames),
+        StopReason::Halted => println!("Halted after {cycles} cycles"),
+        StopReason::Error(e) => anyhow::bail!("Failed after {cycles} cycles: {e}"),
+        StopReason::CycleLimit => {}
+    }
+
+    for row in chip8::vram_rows(&chip8.vram) {
+        println!("{row}");
+    }
+
+    let call_stats = chip8.call_stats();
+    println!("Subroutines: {call_stats}");
+    if let Some(warning) = call_stats.unbalanced_warning() {
+        println!("{warning}");
+    }
+
+    Ok(())
+}
@@ -32,6 +32,9 @@
 
           vulkan-loader
 
+          # audio output
+          alsa-lib
+
           makeWrapper
         ];
       in
@@ -42,6 +45,7 @@
             src = ./.;
             nativeBuildInputs = with pkgs; [
               xorg.libX11
+              pkg-config
             ];
 
             buildInputs = buildInputs;
@@ -59,6 +63,7 @@
           {
             nativeBuildInputs = with pkgs; [
               toolchain
+              pkg-config
             ];
 
             buildInputs = buildInputs;
@@ -0,0 +1,91 @@
+use std::cell::Cell;
+
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    SampleFormat, Stream,
+};
+
+/// Pitch of the buzzer, in Hz
+const BEEP_FREQUENCY: f32 = 440.0;
+
+/// Loudness of the square wave, 1.0 is the loudest the output can play
+const BEEP_VOLUME: f32 = 0.1;
+
+/// The buzzer that sounds while the sound timer is above 0. The output stream is opened once and
+/// only paused in between beeps, so starting a beep doesn't wait for the audio device.
+///
+/// The stream can't be sent to another thread on every platform, so it lives on the thread that
+/// created it
+pub struct Audio {
+    stream: Stream,
+    /// whether the stream is currently playing, so repeated calls don't touch the stream
+    playing: Cell<bool>,
+}
+
+impl Audio {
+    /// Open the default output device, paused
+    pub fn new() -> anyhow::Result<Self> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| anyhow::anyhow!("There is no audio output device"))?;
+        let config = device.default_output_config()?;
+        anyhow::ensure!(
+            config.sample_format() == SampleFormat::F32,
+            "The audio output uses {} samples, only f32 is supported",
+            config.sample_format()
+        );
+
+        #[allow(clippy::cast_precision_loss)]
+        let sample_rate = config.sample_rate().0 as f32;
+        let channels = usize::from(config.channels());
+        // position inside of the current period of the square wave, from 0 to 1
+        let mut phase = 0.0_f32;
+
+        let stream = device.build_output_stream(
+            &config.into(),
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                for frame in data.chunks_mut(channels) {
+                    let sample = if phase < 0.5 {
+                        BEEP_VOLUME
+                    } else {
+                        -BEEP_VOLUME
+                    };
+                    frame.fill(sample);
+                    phase = (phase + BEEP_FREQUENCY / sample_rate) % 1.0;
+                }
+            },
+            |e| log::error!("Audio output failed: {e}"),
+            None,
+        )?;
+        stream.pause()?;
+
+        Ok(Audio {
+            stream,
+            playing: Cell::new(false),
+        })
+    }
+
+    /// Start the tone, does nothing if it is already playing
+    pub fn start(&self) {
+        if !self.playing.replace(true) {
+            if let Err(e) = self.stream.play() {
+                log::error!("Failed to start the beep: {e}");
+            }
+        }
+    }
+
+    /// Stop the tone, does nothing if it is not playing
+    pub fn stop(&self) {
+        if self.playing.replace(false) {
+            if let Err(e) = self.stream.pause() {
+                log::error!("Failed to stop the beep: {e}");
+            }
+        }
+    }
+}
+
+impl Drop for Audio {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
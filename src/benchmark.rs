@@ -0,0 +1,59 @@
+use std::time::{Duration, Instant};
+
+use crate::{chip8::Chip8, selftest};
+
+/// How long the ROM is executed
+const BENCHMARK_DURATION: Duration = Duration::from_secs(5);
+
+/// Run a ROM, or the self test ROM, as fast as possible without opening a window and print how many
+/// instructions per second were executed and how long single instructions took.
+///
+/// The delay timer still ticks every `cycles_per_tick` instructions, so the ROM behaves like it
+/// would at the normal speed, just faster
+pub fn run(rom_file: Option<&str>, cycles_per_tick: u32) -> anyhow::Result<()> {
+    let rom = match rom_file {
+        Some(rom_file) => std::fs::read(rom_file)?,
+        None => selftest::generate_rom(),
+    };
+    let mut chip8 = Chip8::new();
+    chip8.load_rom_bytes(&rom)?;
+
+    log::info!(
+        "Running {} for {BENCHMARK_DURATION:?}",
+        rom_file.unwrap_or("the self test ROM")
+    );
+
+    let mut cycles: u32 = 0;
+    let mut min = Duration::MAX;
+    let mut max = Duration::ZERO;
+    let start = Instant::now();
+
+    while start.elapsed() < BENCHMARK_DURATION {
+        // nobody is there to press a key
+        if let Some(register) = chip8.waiting_for_key.take() {
+            chip8.registers[register] = 0;
+        }
+
+        let instruction_start = Instant::now();
+        chip8.step_cycle()?;
+        let instruction_time = instruction_start.elapsed();
+
+        min = min.min(instruction_time);
+        max = max.max(instruction_time);
+        cycles += 1;
+
+        if cycles.is_multiple_of(cycles_per_tick) {
+            chip8.delay_timer = chip8.delay_timer.saturating_sub(1);
+            chip8.sound_timer = chip8.sound_timer.saturating_sub(1);
+        }
+    }
+
+    let elapsed = start.elapsed();
+    log::info!(
+        "{cycles} instructions in {elapsed:?}: {:.0} instructions/s, per instruction min {min:?}, avg {:?}, max {max:?}",
+        f64::from(cycles) / elapsed.as_secs_f64(),
+        elapsed / cycles.max(1)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::Chip8;
+
+/// Condition under which the interpreter is paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Condition {
+    /// the program counter reached the address
+    Pc(usize),
+    /// the register holds the value. A register that doesn't exist, e.g. from a hand edited
+    /// session file, never does
+    Register { register: usize, value: u8 },
+    /// the address register I holds the value
+    AddressRegister(u16),
+}
+
+impl Condition {
+    pub fn is_met(&self, chip8: &Chip8) -> bool {
+        match *self {
+            Condition::Pc(address) => chip8.pc == address,
+            Condition::Register { register, value } => {
+                chip8.registers.get(register) == Some(&value)
+            }
+            Condition::AddressRegister(value) => chip8.address_register == value,
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Condition::Pc(address) => write!(f, "PC == 0x{address:X}"),
+            Condition::Register { register, value } => write!(f, "V{register:X} == 0x{value:X}"),
+            Condition::AddressRegister(value) => write!(f, "I == 0x{value:X}"),
+        }
+    }
+}
+
+pub struct Breakpoint {
+    pub condition: Condition,
+    /// whether the condition was met after the last instruction.
+    /// A breakpoint only fires when its condition changes from unmet to met, otherwise
+    /// the interpreter would be paused again right after continuing
+    met: bool,
+    /// removed after it fired once, e.g. to run to an address
+    pub one_shot: bool,
+}
+
+impl Breakpoint {
+    /// Create a breakpoint that does not fire before its condition changes
+    pub fn new(condition: Condition, chip8: &Chip8) -> Self {
+        Breakpoint {
+            condition,
+            met: condition.is_met(chip8),
+            one_shot: false,
+        }
+    }
+
+    /// Create a breakpoint that is removed after it fired once
+    pub fn one_shot(condition: Condition, chip8: &Chip8) -> Self {
+        Breakpoint {
+            one_shot: true,
+            ..Breakpoint::new(condition, chip8)
+        }
+    }
+
+    /// Evaluate the condition, returns true if the breakpoint fires
+    pub fn update(&mut self, chip8: &Chip8) -> bool {
+        let was_met = self.met;
+        self.met = self.condition.is_met(chip8);
+
+        self.met && !was_met
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A breakpoint on a register value fires on the instruction that reaches the value, and
+    /// not again while the register keeps it
+    #[test]
+    fn register_breakpoint_fires_once() {
+        let mut chip8 = Chip8::new();
+        let mut breakpoint = Breakpoint::new(
+            Condition::Register {
+                register: 0x3,
+                value: 5,
+            },
+            &chip8,
+        );
+
+        let mut fired = Vec::new();
+        for value in [1, 2, 3, 4, 5, 5, 5, 6, 7] {
+            chip8.registers[0x3] = value;
+            if breakpoint.update(&chip8) {
+                fired.push(value);
+            }
+        }
+        assert_eq!(fired, [5], "Values the breakpoint fired at");
+    }
+
+    /// A condition on a register that doesn't exist, as a session file can contain it, is never
+    /// met instead of crashing the interpreter
+    #[test]
+    fn missing_register() {
+        let condition: Condition =
+            serde_json::from_str(r#"{"Register": {"register": 16, "value": 0}}"#).unwrap();
+        let mut chip8 = Chip8::new();
+        let mut breakpoint = Breakpoint::new(condition, &chip8);
+
+        assert!(!condition.is_met(&chip8), "Condition on V16");
+        chip8.registers[0xF] = 1;
+        assert!(!breakpoint.update(&chip8), "Breakpoint on V16 fired");
+    }
+}
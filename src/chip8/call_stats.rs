@@ -0,0 +1,45 @@
+use std::fmt::Display;
+
+/// Subroutine calls and returns since the interpreter was created, see [`super::Chip8::call_stats`].
+/// A run that ends with frames on the stack hints at a ROM that leaks stack frames, or at a run
+/// that was stopped inside of a subroutine
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallStats {
+    /// executed `2NNN`
+    pub calls: u64,
+    /// executed `00EE`, only counted if there was something to return to
+    pub returns: u64,
+    /// the deepest the stack has been
+    pub max_depth: usize,
+    /// return addresses on the stack when the stats were taken
+    pub depth: usize,
+}
+
+impl CallStats {
+    /// Count a call that made the stack `depth` deep
+    pub(super) fn record_call(&mut self, depth: usize) {
+        self.calls += 1;
+        self.max_depth = self.max_depth.max(depth);
+    }
+
+    /// A warning for the end of a run if the stack is not empty
+    pub fn unbalanced_warning(&self) -> Option<String> {
+        (self.depth > 0).then(|| {
+            format!(
+                "The run ended with {} return addresses on the stack ({self}). \
+                 The ROM might call subroutines without returning, or was stopped inside of one",
+                self.depth
+            )
+        })
+    }
+}
+
+impl Display for CallStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} calls, {} returns, at most {} deep",
+            self.calls, self.returns, self.max_depth
+        )
+    }
+}
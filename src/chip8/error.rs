@@ -0,0 +1,71 @@
+use std::fmt::Display;
+
+/// Why loading a ROM or executing an instruction failed. Frontends usually just show it, but
+/// embedders and tests can match on the kind of fault, e.g. to tell a ROM that returns without
+/// a call from one that reads past the end of memory.
+///
+/// The stack has no size limit, so there is no overflow to report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmuError {
+    /// a ROM without bytes
+    RomEmpty,
+    /// the ROM doesn't fit into memory behind [`super::PC_INIT`]
+    RomTooLarge { len: usize, available: usize },
+    /// the instruction at `addr` would be read from outside of memory
+    PcOutOfBounds { addr: usize },
+    /// the PC ran off the end of memory and the interpreter halted, see
+    /// [`super::quirks::PcOverflow::Halt`]
+    Halted { addr: usize },
+    /// the word at `addr` is no instruction
+    UnknownOpcode { raw: u16, addr: usize },
+    /// `00EE` without a matching call
+    StackUnderflow,
+    /// reading or writing `len` bytes at `addr` reaches past the end of memory
+    MemoryOutOfBounds { addr: usize, len: usize },
+    /// adding `value` to I would point outside of memory, see
+    /// [`super::quirks::Quirks::wrap_address_register`]
+    AddressRegisterOutOfBounds { address_register: u16, value: u16 },
+    /// `BNNN` jumps to `target`, which is outside of memory
+    JumpOutOfBounds { target: usize },
+}
+
+impl Display for EmuError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmuError::RomEmpty => write!(f, "ROM is empty"),
+            EmuError::RomTooLarge { len, available } => write!(
+                f,
+                "ROM is too large ({len} bytes), only {available} bytes are available"
+            ),
+            EmuError::PcOutOfBounds { addr } => write!(
+                f,
+                "Can't read an instruction at 0x{addr:X}, it is outside of memory"
+            ),
+            EmuError::Halted { addr } => {
+                write!(f, "The PC ran off the end of memory at 0x{addr:X}, halting")
+            }
+            EmuError::UnknownOpcode { raw, addr } => {
+                write!(f, "unknown instruction 0x{raw:X} at 0x{addr:X}")
+            }
+            EmuError::StackUnderflow => {
+                write!(f, "Can't return from a subroutine, the stack is empty")
+            }
+            EmuError::MemoryOutOfBounds { addr, len } => write!(
+                f,
+                "Accessing {len} bytes at 0x{addr:X} reaches past the end of memory"
+            ),
+            EmuError::AddressRegisterOutOfBounds {
+                address_register,
+                value,
+            } => write!(
+                f,
+                "I = 0x{address_register:X} + 0x{value:X} points outside of memory"
+            ),
+            EmuError::JumpOutOfBounds { target } => {
+                write!(f, "Jump to 0x{target:X} is outside of memory")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EmuError {}
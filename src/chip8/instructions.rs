@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, Copy)]
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Instruction {
     ///00E0
     Clear,
@@ -106,11 +108,11 @@ pub enum Instruction {
         register_x: usize,
         mask: u8,
     },
-    ///EX9E
+    ///EX9E, VX values > 0xF are keys that don't exist and are never down, so it never skips
     SkipIfKey {
         register_x: usize,
     },
-    ///EXA1
+    ///EXA1, VX values > 0xF are keys that don't exist and are never down, so it always skips
     SkipIfNotKey {
         register_x: usize,
     },
@@ -130,6 +132,10 @@ pub enum Instruction {
     SetDelayTimer {
         register_x: usize,
     },
+    ///FX18
+    SetSoundTimer {
+        register_x: usize,
+    },
     ///FX07
     ReadDelayTimer {
         register_x: usize,
@@ -146,6 +152,18 @@ pub enum Instruction {
     LoadRegisters {
         register_x: usize,
     },
+    /// an opcode that can't be decoded, e.g. sprite data between the code.
+    /// Only created by [`Instruction::try_from_lenient`], executing it fails unless
+    /// [`super::quirks::Quirks::skip_unknown_opcodes`] is set
+    Unknown(u16),
+}
+
+impl Instruction {
+    /// Decode an opcode like [`Instruction::try_from`], but return [`Instruction::Unknown`]
+    /// instead of an error, for tools like the disassembler that show every word of memory
+    pub fn try_from_lenient(opcode: u16) -> Instruction {
+        Instruction::try_from(opcode).unwrap_or(Instruction::Unknown(opcode))
+    }
 }
 
 impl TryFrom<u16> for Instruction {
@@ -249,6 +267,7 @@ impl TryFrom<u16> for Instruction {
             (0xF, _, 0x0, 0x7) => Ok(Instruction::ReadDelayTimer { register_x: x }),
             (0xF, _, 0x0, 0xA) => Ok(Instruction::WaitForKey { register_x: x }),
             (0xF, _, 0x1, 0x5) => Ok(Instruction::SetDelayTimer { register_x: x }),
+            (0xF, _, 0x1, 0x8) => Ok(Instruction::SetSoundTimer { register_x: x }),
             (0xF, _, 0x1, 0xE) => Ok(Instruction::AddXtoI { register_x: x }),
             (0xF, _, 0x2, 0x9) => Ok(Instruction::LoadFontCharacter { register_x: x }),
             (0xF, _, 0x5, 0x5) => Ok(Instruction::StoreRegisters { register_x: x }),
@@ -259,6 +278,148 @@ impl TryFrom<u16> for Instruction {
     }
 }
 
+/// An entry of the instruction set table, see [`supported_opcodes`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpcodeInfo {
+    /// the opcode with its operands as letters, e.g. `8XY4`
+    pub pattern: &'static str,
+    pub mnemonic: &'static str,
+    pub description: &'static str,
+    /// the bits of an opcode that are fixed by the pattern, and their values
+    mask: u16,
+    value: u16,
+}
+
+impl OpcodeInfo {
+    /// Build an entry, the mask and value are taken from the hex digits of the pattern
+    const fn new(pattern: &'static str, mnemonic: &'static str, description: &'static str) -> Self {
+        let digits = pattern.as_bytes();
+        let mut mask = 0;
+        let mut value = 0;
+
+        let mut i = 0;
+        while i < 4 {
+            let nibble = match digits[i] {
+                b'0'..=b'9' => Some(digits[i] - b'0'),
+                b'A'..=b'F' => Some(digits[i] - b'A' + 10),
+                _ => None,
+            };
+            mask <<= 4;
+            value <<= 4;
+            if let Some(nibble) = nibble {
+                mask |= 0xF;
+                value |= nibble as u16;
+            }
+            i += 1;
+        }
+
+        OpcodeInfo {
+            pattern,
+            mnemonic,
+            description,
+            mask,
+            value,
+        }
+    }
+
+    /// Whether the opcode has the fixed digits of the pattern
+    pub fn matches(&self, opcode: u16) -> bool {
+        opcode & self.mask == self.value
+    }
+
+    /// The table entry of an opcode, [None] for opcodes that are not supported
+    pub fn find(opcode: u16) -> Option<&'static OpcodeInfo> {
+        supported_opcodes().iter().find(|info| info.matches(opcode))
+    }
+}
+
+const OPCODES: [OpcodeInfo; 34] = [
+    OpcodeInfo::new("00E0", "CLS", "Clear the screen"),
+    OpcodeInfo::new("00EE", "RET", "Return from a subroutine"),
+    OpcodeInfo::new("1NNN", "JP", "Jump to NNN"),
+    OpcodeInfo::new("2NNN", "CALL", "Call the subroutine at NNN"),
+    OpcodeInfo::new("3XNN", "SE", "Skip the next instruction if VX == NN"),
+    OpcodeInfo::new("4XNN", "SNE", "Skip the next instruction if VX != NN"),
+    OpcodeInfo::new("5XY0", "SE", "Skip the next instruction if VX == VY"),
+    OpcodeInfo::new("6XNN", "LD", "Set VX to NN"),
+    OpcodeInfo::new("7XNN", "ADD", "Add NN to VX, VF is not changed"),
+    OpcodeInfo::new("8XY0", "LD", "Set VX to VY"),
+    OpcodeInfo::new("8XY1", "OR", "Set VX to VX | VY"),
+    OpcodeInfo::new("8XY2", "AND", "Set VX to VX & VY"),
+    OpcodeInfo::new("8XY3", "XOR", "Set VX to VX ^ VY"),
+    OpcodeInfo::new("8XY4", "ADD", "Add VY to VX, VF is set to the carry"),
+    OpcodeInfo::new(
+        "8XY5",
+        "SUB",
+        "Subtract VY from VX, VF is set to 1 without a borrow (VX >= VY) and 0 on a borrow",
+    ),
+    OpcodeInfo::new(
+        "8XY6",
+        "SHR",
+        "Shift right by one, VF is set to the shifted out bit",
+    ),
+    OpcodeInfo::new(
+        "8XY7",
+        "SUBN",
+        "Set VX to VY - VX, VF is set to 1 without a borrow (VY >= VX) and 0 on a borrow",
+    ),
+    OpcodeInfo::new(
+        "8XYE",
+        "SHL",
+        "Shift left by one, VF is set to the shifted out bit",
+    ),
+    OpcodeInfo::new("9XY0", "SNE", "Skip the next instruction if VX != VY"),
+    OpcodeInfo::new("ANNN", "LD", "Set I to NNN"),
+    OpcodeInfo::new(
+        "BNNN",
+        "JP",
+        "Jump to NNN + V0, or NNN + VX with the SCHIP quirk",
+    ),
+    OpcodeInfo::new("CXNN", "RND", "Set VX to a random number masked with NN"),
+    OpcodeInfo::new(
+        "DXYN",
+        "DRW",
+        "Draw the N bytes long sprite at I to VX, VY, VF is set on a collision",
+    ),
+    OpcodeInfo::new(
+        "EX9E",
+        "SKP",
+        "Skip the next instruction if the key in VX is down",
+    ),
+    OpcodeInfo::new(
+        "EXA1",
+        "SKNP",
+        "Skip the next instruction if the key in VX is up",
+    ),
+    OpcodeInfo::new("FX07", "LD", "Set VX to the delay timer"),
+    OpcodeInfo::new("FX0A", "LD", "Wait for a key press and store the key in VX"),
+    OpcodeInfo::new("FX15", "LD", "Set the delay timer to VX"),
+    OpcodeInfo::new(
+        "FX18",
+        "LD",
+        "Set the sound timer to VX, the buzzer sounds while it is above 0",
+    ),
+    OpcodeInfo::new("FX1E", "ADD", "Add VX to I"),
+    OpcodeInfo::new(
+        "FX29",
+        "LD",
+        "Set I to the font character for the digit in VX",
+    ),
+    OpcodeInfo::new(
+        "FX33",
+        "LD",
+        "Store the decimal digits of VX at I, I + 1 and I + 2",
+    ),
+    OpcodeInfo::new("FX55", "LD", "Store V0 to VX at I"),
+    OpcodeInfo::new("FX65", "LD", "Load V0 to VX from I"),
+];
+
+/// Every opcode the decoder knows, with its assembler mnemonic and what it does.
+/// It is written by hand, `cargo test` checks that the table and the decoder agree on every opcode
+pub fn supported_opcodes() -> &'static [OpcodeInfo] {
+    &OPCODES
+}
+
 fn read_address(instruction: u16) -> u16 {
     instruction & 0x0FFF
 }
@@ -266,3 +427,206 @@ fn read_address(instruction: u16) -> u16 {
 fn read_byte_operand(instruction: u16) -> u8 {
     (instruction & 0x00FF) as u8
 }
+
+impl From<Instruction> for u16 {
+    /// Encode the instruction back into its opcode
+    fn from(instruction: Instruction) -> Self {
+        match instruction {
+            Instruction::Clear => 0x00E0,
+            Instruction::Return => 0x00EE,
+            Instruction::JumpToAddress { address } => 0x1000 | encode_address(address),
+            Instruction::ExecuteSubroutine { address } => 0x2000 | encode_address(address),
+            Instruction::SkipIfRegisterEqTo { register, value } => {
+                0x3000 | encode_x(register.into()) | u16::from(value)
+            }
+            Instruction::SkipIfRegisterNeqTo { register, value } => {
+                0x4000 | encode_x(register.into()) | u16::from(value)
+            }
+            Instruction::SkipIfRegistersEq {
+                register_x,
+                register_y,
+            } => 0x5000 | encode_xy(register_x, register_y),
+            Instruction::StoreNumberInRegister { number, register } => {
+                0x6000 | encode_x(register.into()) | u16::from(number)
+            }
+            Instruction::AddToRegister { register, value } => {
+                0x7000 | encode_x(register.into()) | u16::from(value)
+            }
+            Instruction::CopyRegister {
+                register_x,
+                register_y,
+            } => 0x8000 | encode_xy(register_x, register_y),
+            Instruction::OrRegisters {
+                register_x,
+                register_y,
+            } => 0x8001 | encode_xy(register_x, register_y),
+            Instruction::AndRegisters {
+                register_x,
+                register_y,
+            } => 0x8002 | encode_xy(register_x, register_y),
+            Instruction::XorRegisters {
+                register_x,
+                register_y,
+            } => 0x8003 | encode_xy(register_x, register_y),
+            Instruction::AddRegisters {
+                register_x,
+                register_y,
+            } => 0x8004 | encode_xy(register_x, register_y),
+            Instruction::SubRegisters {
+                register_x,
+                register_y,
+            } => 0x8005 | encode_xy(register_x, register_y),
+            Instruction::RightShiftRegister {
+                register_x,
+                register_y,
+            } => 0x8006 | encode_xy(register_x, register_y),
+            Instruction::SubRegistersOtherWayArround {
+                register_x,
+                register_y,
+            } => 0x8007 | encode_xy(register_x, register_y),
+            Instruction::LeftShiftRegister {
+                register_x,
+                register_y,
+            } => 0x800E | encode_xy(register_x, register_y),
+            Instruction::SkipIfRegistersNeq {
+                register_x,
+                register_y,
+            } => 0x9000 | encode_xy(register_x, register_y),
+            Instruction::SetAddressRegister { address } => 0xA000 | encode_address(address),
+            Instruction::JumpOffsetV0 { address } => 0xB000 | encode_address(address),
+            Instruction::RandomNumber { register_x, mask } => {
+                0xC000 | encode_x(register_x) | u16::from(mask)
+            }
+            Instruction::DrawSprite {
+                register_x,
+                register_y,
+                len,
+            } => 0xD000 | encode_xy(register_x, register_y) | u16::from(len & 0xF),
+            Instruction::SkipIfKey { register_x } => 0xE09E | encode_x(register_x),
+            Instruction::SkipIfNotKey { register_x } => 0xE0A1 | encode_x(register_x),
+            Instruction::ReadDelayTimer { register_x } => 0xF007 | encode_x(register_x),
+            Instruction::WaitForKey { register_x } => 0xF00A | encode_x(register_x),
+            Instruction::SetDelayTimer { register_x } => 0xF015 | encode_x(register_x),
+            Instruction::SetSoundTimer { register_x } => 0xF018 | encode_x(register_x),
+            Instruction::AddXtoI { register_x } => 0xF01E | encode_x(register_x),
+            Instruction::LoadFontCharacter { register_x } => 0xF029 | encode_x(register_x),
+            Instruction::BinaryCodedDecimal { register_x } => 0xF033 | encode_x(register_x),
+            Instruction::StoreRegisters { register_x } => 0xF055 | encode_x(register_x),
+            Instruction::LoadRegisters { register_x } => 0xF065 | encode_x(register_x),
+            Instruction::Unknown(opcode) => opcode,
+        }
+    }
+}
+
+/// Encode the instructions into a ROM
+pub fn assemble(program: impl IntoIterator<Item = Instruction>) -> Vec<u8> {
+    program
+        .into_iter()
+        .flat_map(|instruction| u16::from(instruction).to_be_bytes())
+        .collect()
+}
+
+impl Display for Instruction {
+    /// The assembler mnemonic, e.g. `LD V1, 0x2A`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Instruction::Clear => write!(f, "CLS"),
+            Instruction::Return => write!(f, "RET"),
+            Instruction::JumpToAddress { address } => write!(f, "JP 0x{address:03X}"),
+            Instruction::ExecuteSubroutine { address } => write!(f, "CALL 0x{address:03X}"),
+            Instruction::SkipIfRegisterEqTo { register, value } => {
+                write!(f, "SE V{register:X}, 0x{value:02X}")
+            }
+            Instruction::SkipIfRegisterNeqTo { register, value } => {
+                write!(f, "SNE V{register:X}, 0x{value:02X}")
+            }
+            Instruction::SkipIfRegistersEq {
+                register_x,
+                register_y,
+            } => write!(f, "SE V{register_x:X}, V{register_y:X}"),
+            Instruction::StoreNumberInRegister { number, register } => {
+                write!(f, "LD V{register:X}, 0x{number:02X}")
+            }
+            Instruction::AddToRegister { register, value } => {
+                write!(f, "ADD V{register:X}, 0x{value:02X}")
+            }
+            Instruction::CopyRegister {
+                register_x,
+                register_y,
+            } => write!(f, "LD V{register_x:X}, V{register_y:X}"),
+            Instruction::OrRegisters {
+                register_x,
+                register_y,
+            } => write!(f, "OR V{register_x:X}, V{register_y:X}"),
+            Instruction::AndRegisters {
+                register_x,
+                register_y,
+            } => write!(f, "AND V{register_x:X}, V{register_y:X}"),
+            Instruction::XorRegisters {
+                register_x,
+                register_y,
+            } => write!(f, "XOR V{register_x:X}, V{register_y:X}"),
+            Instruction::AddRegisters {
+                register_x,
+                register_y,
+            } => write!(f, "ADD V{register_x:X}, V{register_y:X}"),
+            Instruction::SubRegisters {
+                register_x,
+                register_y,
+            } => write!(f, "SUB V{register_x:X}, V{register_y:X}"),
+            Instruction::RightShiftRegister {
+                register_x,
+                register_y,
+            } => write!(f, "SHR V{register_x:X}, V{register_y:X}"),
+            Instruction::SubRegistersOtherWayArround {
+                register_x,
+                register_y,
+            } => write!(f, "SUBN V{register_x:X}, V{register_y:X}"),
+            Instruction::LeftShiftRegister {
+                register_x,
+                register_y,
+            } => write!(f, "SHL V{register_x:X}, V{register_y:X}"),
+            Instruction::SkipIfRegistersNeq {
+                register_x,
+                register_y,
+            } => write!(f, "SNE V{register_x:X}, V{register_y:X}"),
+            Instruction::SetAddressRegister { address } => write!(f, "LD I, 0x{address:03X}"),
+            Instruction::JumpOffsetV0 { address } => write!(f, "JP V0, 0x{address:03X}"),
+            Instruction::RandomNumber { register_x, mask } => {
+                write!(f, "RND V{register_x:X}, 0x{mask:02X}")
+            }
+            Instruction::DrawSprite {
+                register_x,
+                register_y,
+                len,
+            } => write!(f, "DRW V{register_x:X}, V{register_y:X}, {len}"),
+            Instruction::SkipIfKey { register_x } => write!(f, "SKP V{register_x:X}"),
+            Instruction::SkipIfNotKey { register_x } => write!(f, "SKNP V{register_x:X}"),
+            Instruction::ReadDelayTimer { register_x } => write!(f, "LD V{register_x:X}, DT"),
+            Instruction::WaitForKey { register_x } => write!(f, "LD V{register_x:X}, K"),
+            Instruction::SetDelayTimer { register_x } => write!(f, "LD DT, V{register_x:X}"),
+            Instruction::SetSoundTimer { register_x } => write!(f, "LD ST, V{register_x:X}"),
+            Instruction::AddXtoI { register_x } => write!(f, "ADD I, V{register_x:X}"),
+            Instruction::LoadFontCharacter { register_x } => write!(f, "LD F, V{register_x:X}"),
+            Instruction::BinaryCodedDecimal { register_x } => write!(f, "LD B, V{register_x:X}"),
+            Instruction::StoreRegisters { register_x } => write!(f, "LD [I], V{register_x:X}"),
+            Instruction::LoadRegisters { register_x } => write!(f, "LD V{register_x:X}, [I]"),
+            Instruction::Unknown(opcode) => {
+                let [hi, lo] = opcode.to_be_bytes();
+                write!(f, "DB 0x{hi:02X}, 0x{lo:02X}")
+            }
+        }
+    }
+}
+
+fn encode_address(address: u16) -> u16 {
+    address & 0x0FFF
+}
+
+fn encode_x(register_x: usize) -> u16 {
+    u16::try_from(register_x & 0xF).unwrap() << 8
+}
+
+fn encode_xy(register_x: usize, register_y: usize) -> u16 {
+    encode_x(register_x) | (u16::try_from(register_y & 0xF).unwrap() << 4)
+}
@@ -1,8 +1,32 @@
+pub mod breakpoint;
+pub mod call_stats;
+pub mod error;
 pub mod instructions;
-
-use std::path::Path;
-
-use self::instructions::Instruction;
+pub mod platform;
+pub mod quirks;
+pub mod rng;
+pub mod rom_info;
+pub mod run;
+pub mod snapshot;
+
+use std::{
+    collections::{hash_map::DefaultHasher, VecDeque},
+    hash::{Hash, Hasher},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use self::{
+    breakpoint::{Breakpoint, Condition},
+    call_stats::CallStats,
+    error::EmuError,
+    instructions::Instruction,
+    quirks::{PcOverflow, Quirks},
+    rng::{DefaultRng, RandomSource},
+    rom_info::RomInfo,
+    snapshot::Snapshot,
+};
 
 pub const DISPLAY_WIDTH: u16 = 64;
 pub const DISPLAY_HEIGHT: u16 = 32;
@@ -10,12 +34,32 @@ pub const DISPLAY_HEIGHT: u16 = 32;
 /// Initital program counter value and the offset at which the rom is loaded into memory
 pub const PC_INIT: usize = 0x200;
 
+/// 4 KB like the original interpreter and the SCHIP
+pub const DEFAULT_MEMORY_SIZE: usize = 0x1000;
+/// 64 KB like the XO-CHIP, the most I can address
+pub const MAX_MEMORY_SIZE: usize = 0x10000;
+
+/// How many instructions at the start of a ROM are checked by [`Chip8::early_return`]
+const EARLY_RETURN_SCAN_LEN: usize = 16;
+
 pub const DELAY_TIMER_FREQUENCY: f32 = 60.0; // hz;
 
+/// How many instructions are executed between two timer ticks, at least one. Frontends count
+/// executed instructions instead of measuring time, so the timers keep their pace relative to the
+/// ROM when the interpreter falls behind or is single stepped
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn cycles_per_timer_tick(instruction_frequency: f32, timer_frequency: f32) -> u32 {
+    ((instruction_frequency / timer_frequency).floor() as u32).max(1)
+}
+
 pub const LOG_TARGET_INPUT: &str = "INPUT";
 pub const LOG_TARGET_INSTRUCTIONS: &str = "INSTR";
 pub const LOG_TARGET_DRAWING: &str = "DRAW";
 pub const LOG_TARGET_TIMER: &str = "TIMER";
+pub const LOG_TARGET_SOUND: &str = "SOUND";
+pub const LOG_TARGET_VBLANK: &str = "VBLANK";
+pub const LOG_TARGET_DEBUGGER: &str = "DEBUGGER";
+pub const LOG_TARGET_FLAGS: &str = "FLAGS";
 
 /// <https://github.com/mattmikolay/chip-8/wiki/CHIP%E2%80%908-Technical-Reference#fonts>
 const FONT: [u8; 80] = [
@@ -26,24 +70,38 @@ const FONT: [u8; 80] = [
     0xF0, 0xE0, 0x90, 0x90, 0x90, 0xE0, 0xF0, 0x80, 0xF0, 0x80, 0xF0, 0xF0, 0x80, 0xF0, 0x80, 0x80,
 ];
 
-const FONT_START: usize = 0x0;
+pub const FONT_START: usize = 0x0;
+/// first address behind the font
+pub const FONT_END: usize = FONT_START + FONT.len();
 const FONT_BYTES_PER_CHAR: usize = 5;
 
 #[derive(Default)]
 pub struct Keyboard(u16);
 
 impl Keyboard {
+    /// Bit representing the key, None for keys > 0xF. Key numbers come from registers,
+    /// so a ROM can ask for keys that don't exist
+    fn key_bit(key: u8) -> Option<u16> {
+        1_u16.checked_shl(u32::from(key))
+    }
+
+    /// Does nothing for keys > 0xF
     pub fn set_down(&mut self, key: u8) {
-        self.0 |= 2_u16.pow(u32::from(key));
+        if let Some(v) = Self::key_bit(key) {
+            self.0 |= v;
+        }
     }
 
+    /// Does nothing for keys > 0xF
     pub fn set_up(&mut self, key: u8) {
-        self.0 ^= 2_u16.pow(u32::from(key));
+        if let Some(v) = Self::key_bit(key) {
+            self.0 ^= v;
+        }
     }
 
+    /// Keys > 0xF are never down
     pub fn is_down(&self, key: u8) -> bool {
-        let v = 2_u16.pow(u32::from(key));
-        self.0 & v == v
+        Self::key_bit(key).is_some_and(|v| self.0 & v == v)
     }
 
     pub fn reset(&mut self) {
@@ -65,67 +123,313 @@ impl Keyboard {
     }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+/// A key going down or up, see [`Chip8::queue_key_event`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub key: u8,
+    pub down: bool,
+}
+
+/// What a single [`Chip8::step_cycle`] did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepOutcome {
+    pub instruction: Instruction,
+    /// the screen changed, e.g. by `00E0` or `DXYN`
+    pub redrew: bool,
+    /// `DXYN` detected a collision and set VF, see [`Quirks::collision`]
+    pub collision: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub enum Mode {
     Running,
-    WaitForKey { register: usize },
     Paused,
+    /// stopped for good because the PC ran off the end of memory, see [`PcOverflow::Halt`]
+    Halted,
 }
 
+/// Called with every instruction right before it is executed, see [`Chip8::set_pre_execute_hook`]
+pub type PreExecuteHook = Box<dyn FnMut(&Chip8, &Instruction) + Send>;
+
+#[allow(clippy::struct_excessive_bools)]
 pub struct Chip8 {
-    pub memory: [u8; 4096],
+    pub memory: Vec<u8>,
+    /// which memory bytes were written by instructions, see [`Chip8::written_memory`]
+    written_memory: Vec<bool>,
     pub registers: [u8; 16],
     pub pc: usize,
     pub address_register: u16,
     pub vram: [u8; DISPLAY_WIDTH as usize * DISPLAY_HEIGHT as usize],
     stack: Vec<usize>,
+    /// see [`Chip8::call_stats`]
+    call_stats: CallStats,
     pub keyboard: Keyboard,
+    /// key events that were not applied to the keyboard yet, oldest first
+    key_events: VecDeque<KeyEvent>,
     pub delay_timer: u8,
-    /// indicates whether there was a change to the vram, indicating the screen should be
-    /// re-rendered. The rendering application has to set this back to false after rendering,
-    /// as this does not happen automatically
-    pub redraw: bool,
+    /// set by `FX18` and decreased at the same rate as the delay timer, the buzzer sounds while it
+    /// is above 0, see [`Chip8::is_sound_playing`]
+    pub sound_timer: u8,
+    /// instructions counted since the last timer tick, see [`Chip8::count_timer_cycle`].
+    /// Part of snapshots, so restored timers tick at the same instruction as before
+    pub timer_cycles: u32,
+    /// timer ticks since the interpreter was created, see [`Chip8::frame_count`]
+    frame_count: u64,
+    /// incremented after every complete change to the vram. The renderer compares it to the version
+    /// it rendered last, so it never needs to look at the vram while a sprite is being drawn and
+    /// also notices changes that were not made by an instruction, e.g. restoring a snapshot
+    pub vram_version: u64,
     pub mode: Mode,
+    /// the register that receives the next released key, set by `FX0A`. Frontends don't execute
+    /// instructions while it is set. It is independent of the mode, so waiting for a key can be
+    /// paused and continues when the interpreter is resumed
+    pub waiting_for_key: Option<usize>,
+    /// set by `DXYN` if [`Quirks::display_wait`] is enabled and cleared by the next timer tick.
+    /// Frontends don't execute instructions while it is set, but keep counting timer cycles
+    pub waiting_for_vblank: bool,
+    pub quirks: Quirks,
+    /// log the inputs, result and VF of every arithmetic instruction that sets VF,
+    /// to find where a ROM expects a different carry or borrow
+    pub log_flags: bool,
+    /// log every instruction that changes VF, with the old and new value
+    pub log_vf_changes: bool,
+    breakpoints: Vec<Breakpoint>,
+    /// how many snapshots taken before `00E0` and `DXYN` are kept for
+    /// [`Chip8::previous_frame`], 0 disables them
+    pub frame_history_len: usize,
+    /// oldest first
+    frame_history: VecDeque<Snapshot>,
+    /// whether the vram contains the test pattern, it is cleared before the first sprite is drawn
+    showing_test_pattern: bool,
+    /// whether the last `DXYN` detected a collision, reported by [`Chip8::step_cycle`] instead of
+    /// reading VF, which a `DXYN` that draws nothing doesn't touch
+    sprite_collided: bool,
+    pre_execute_hook: Option<PreExecuteHook>,
+    /// where `CXNN` takes its numbers from, see [`Chip8::set_rng`]
+    rng: Box<dyn RandomSource>,
+    /// the ROM loaded last, see [`Chip8::rom_info`]
+    rom_info: Option<RomInfo>,
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Chip8 {
     pub fn new() -> Self {
-        let mut memory = [0_u8; 4096];
+        let mut memory = vec![0_u8; DEFAULT_MEMORY_SIZE];
 
         for (i, data) in FONT.iter().enumerate() {
             memory[FONT_START + i] = *data;
         }
 
         Chip8 {
+            written_memory: vec![false; memory.len()],
             memory,
             registers: [0_u8; 16],
             pc: PC_INIT,
             address_register: 0,
             vram: [0_u8; DISPLAY_WIDTH as usize * DISPLAY_HEIGHT as usize],
             stack: Vec::new(),
+            call_stats: CallStats::default(),
             keyboard: Keyboard::default(),
+            key_events: VecDeque::new(),
             delay_timer: 0,
-            redraw: false,
+            sound_timer: 0,
+            timer_cycles: 0,
+            frame_count: 0,
+            vram_version: 0,
             mode: Mode::Running,
+            waiting_for_key: None,
+            waiting_for_vblank: false,
+            quirks: Quirks::default(),
+            log_flags: false,
+            log_vf_changes: false,
+            breakpoints: Vec::new(),
+            frame_history_len: 0,
+            frame_history: VecDeque::new(),
+            showing_test_pattern: false,
+            sprite_collided: false,
+            pre_execute_hook: None,
+            rng: Box::new(DefaultRng::from_entropy()),
+            rom_info: None,
         }
     }
 
+    /// Create an interpreter with `size` bytes of memory, see [`Chip8::set_memory_size`]
+    pub fn with_memory_size(size: usize) -> anyhow::Result<Self> {
+        let mut chip8 = Chip8::new();
+        chip8.set_memory_size(size)?;
+
+        Ok(chip8)
+    }
+
+    /// Set a register before running, e.g. to test a single instruction without a ROM that
+    /// prepares the state:
+    ///
+    /// ```
+    /// use chip8stuff::chip8::{Chip8, PC_INIT};
+    ///
+    /// // 7003: ADD V0, 0x03
+    /// let mut chip8 = Chip8::new()
+    ///     .with_register(0x0, 5)?
+    ///     .with_memory(PC_INIT, &[0x70, 0x03])?;
+    /// chip8.step_cycle()?;
+    ///
+    /// assert_eq!(chip8.registers[0x0], 8);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn with_register(mut self, register: usize, value: u8) -> anyhow::Result<Self> {
+        let len = self.registers.len();
+        let Some(r) = self.registers.get_mut(register) else {
+            anyhow::bail!("There is no register V{register:X}, only {len} registers exist");
+        };
+        *r = value;
+
+        Ok(self)
+    }
+
+    /// Copy `bytes` into memory starting at `address` before running, see [`Chip8::with_register`].
+    /// Unlike [`Chip8::load_rom_bytes`], any address can be written, including the font
+    pub fn with_memory(mut self, address: usize, bytes: &[u8]) -> anyhow::Result<Self> {
+        let memory_len = self.memory.len();
+        let target = address
+            .checked_add(bytes.len())
+            .and_then(|end| self.memory.get_mut(address..end));
+        let Some(target) = target else {
+            anyhow::bail!(
+                "{} bytes at 0x{address:X} don't fit into {memory_len} bytes of memory",
+                bytes.len()
+            );
+        };
+        target.copy_from_slice(bytes);
+
+        Ok(self)
+    }
+
+    /// Grow or shrink the memory, e.g. to the 64 KB of the XO-CHIP. The content is kept as far as
+    /// it fits. The size has to leave room for a ROM and can't be larger than [`MAX_MEMORY_SIZE`]
+    pub fn set_memory_size(&mut self, size: usize) -> anyhow::Result<()> {
+        check_memory_size(size)?;
+        self.memory.resize(size, 0);
+        self.written_memory.resize(size, false);
+
+        Ok(())
+    }
+
     pub fn load_rom(&mut self, file_path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let file_path = file_path.as_ref();
         let rom = std::fs::read(file_path)?;
 
+        self.load_rom_bytes(&rom)?;
+        if let Some(name) = file_path.file_name() {
+            self.set_rom_name(&name.to_string_lossy());
+        }
+
+        Ok(())
+    }
+
+    /// Copy the rom into memory, starting at [`PC_INIT`]
+    pub fn load_rom_bytes(&mut self, rom: &[u8]) -> Result<(), EmuError> {
         let offset = PC_INIT;
-        self.memory[offset..(rom.len() + offset)].copy_from_slice(&rom[..]);
+
+        if rom.is_empty() {
+            return Err(EmuError::RomEmpty);
+        }
+
+        if rom.len() > self.memory.len() - offset {
+            return Err(EmuError::RomTooLarge {
+                len: rom.len(),
+                available: self.memory.len() - offset,
+            });
+        }
+
+        self.memory[offset..(rom.len() + offset)].copy_from_slice(rom);
+        self.rom_info = Some(RomInfo::new(rom));
 
         Ok(())
     }
 
-    fn fetch_and_decode_instruction(&mut self) -> anyhow::Result<Instruction> {
-        let instruction: u16 =
-            u16::from(self.memory[self.pc]) << 8 | u16::from(self.memory[self.pc + 1]);
+    /// Name, size and checksum of the ROM loaded last. None if no ROM was loaded, e.g. when the
+    /// state was restored from a snapshot
+    pub fn rom_info(&self) -> Option<&RomInfo> {
+        self.rom_info.as_ref()
+    }
+
+    /// Name the loaded ROM, for ROMs that were loaded from bytes. Does nothing if no ROM was loaded
+    pub fn set_rom_name(&mut self, name: &str) {
+        if let Some(rom_info) = &mut self.rom_info {
+            rom_info.name = Some(name.to_owned());
+        }
+    }
+
+    /// Read the two bytes of the instruction at `address`
+    pub fn opcode_at(&self, address: usize) -> Result<u16, EmuError> {
+        match self.memory.get(address..address + 2) {
+            Some(&[hi, lo]) => Ok(u16::from(hi) << 8 | u16::from(lo)),
+            _ => Err(EmuError::PcOutOfBounds { addr: address }),
+        }
+    }
+
+    /// Which memory bytes were written by `FX33` or `FX55` since the interpreter was created or a
+    /// snapshot was restored, one entry per byte of memory. Loading the ROM doesn't count
+    pub fn written_memory(&self) -> &[bool] {
+        &self.written_memory
+    }
+
+    /// Look for a `00EE` return among the first instructions of the loaded ROM that comes before
+    /// any `2NNN` call. Returns its address.
+    ///
+    /// Returning without a call almost always means that the ROM is misaligned or was written to
+    /// be loaded at another address. The instructions are checked in order without executing them,
+    /// up to the first jump or an invalid opcode
+    pub fn early_return(&self) -> Option<usize> {
+        for address in (PC_INIT..).step_by(2).take(EARLY_RETURN_SCAN_LEN) {
+            match Instruction::try_from(self.opcode_at(address).ok()?) {
+                Ok(Instruction::Return) => return Some(address),
+                Ok(Instruction::ExecuteSubroutine { .. } | Instruction::JumpToAddress { .. })
+                | Err(_) => return None,
+                Ok(_) => {}
+            }
+        }
+
+        None
+    }
+
+    /// Decode the instruction at the program counter without executing it or advancing the
+    /// program counter
+    pub fn peek_next(&self) -> Result<Instruction, EmuError> {
+        let raw = self.opcode_at(self.pc)?;
+        Instruction::try_from(raw).map_err(|_| EmuError::UnknownOpcode { raw, addr: self.pc })
+    }
+
+    fn fetch_and_decode_instruction(&mut self) -> Result<Instruction, EmuError> {
+        let instruction = match self.quirks.pc_overflow {
+            PcOverflow::Wrap => {
+                self.pc %= self.memory.len();
+                let lo = self.memory[(self.pc + 1) % self.memory.len()];
+                u16::from(self.memory[self.pc]) << 8 | u16::from(lo)
+            }
+            PcOverflow::Halt if self.opcode_at(self.pc).is_err() => {
+                self.mode = Mode::Halted;
+                return Err(EmuError::Halted { addr: self.pc });
+            }
+            PcOverflow::Error | PcOverflow::Halt => self.opcode_at(self.pc)?,
+        };
 
+        let addr = self.pc;
         self.pc += 2;
 
-        let instr = Instruction::try_from(instruction);
+        let instr = if self.quirks.skip_unknown_opcodes {
+            Ok(Instruction::try_from_lenient(instruction))
+        } else {
+            Instruction::try_from(instruction).map_err(|_| EmuError::UnknownOpcode {
+                raw: instruction,
+                addr,
+            })
+        };
 
         if let Ok(i) = &instr {
             log::trace!(target: LOG_TARGET_INSTRUCTIONS, "0x{instruction:X}: {:?}", i);
@@ -134,11 +438,102 @@ impl Chip8 {
         instr
     }
 
-    fn execute_instruction(&mut self, instruction: Instruction) {
+    /// Add to I, wrapping around or failing depending on [`Quirks::wrap_address_register`]
+    fn add_to_address_register(&mut self, value: u16) -> Result<(), EmuError> {
+        if self.quirks.wrap_address_register {
+            self.address_register = self.address_register.wrapping_add(value);
+        } else {
+            self.address_register = self
+                .address_register
+                .checked_add(value)
+                // pointing right behind the last byte is fine, e.g. after storing into it
+                .filter(|address| usize::from(*address) <= self.memory.len())
+                .ok_or(EmuError::AddressRegisterOutOfBounds {
+                    address_register: self.address_register,
+                    value,
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// The `len` bytes of memory starting at I, an error if they reach past the end of memory
+    fn address_register_range(&self, len: usize) -> Result<std::ops::Range<usize>, EmuError> {
+        let start = usize::from(self.address_register);
+        if start + len > self.memory.len() {
+            return Err(EmuError::MemoryOutOfBounds { addr: start, len });
+        }
+
+        Ok(start..start + len)
+    }
+
+    /// Where a sprite coordinate ends up on an axis of `size` pixels, depending on whether the
+    /// axis wraps. Returns [None] if it is clipped, which is also true for every coordinate after it
+    fn sprite_coordinate(coordinate: u16, size: u16, wrap: bool) -> Option<u16> {
+        if wrap {
+            Some(coordinate % size)
+        } else {
+            (coordinate < size).then_some(coordinate)
+        }
+    }
+
+    /// The register that is shifted by 8XY6 and 8XYE, depending on [`Quirks::shift_uses_vy`]
+    fn shift_source(&self, register_x: usize, register_y: usize) -> usize {
+        if self.quirks.shift_uses_vy {
+            register_y
+        } else {
+            register_x
+        }
+    }
+
+    /// Log the inputs of an arithmetic instruction, its result in VX and the VF it computed,
+    /// if enabled with [`Chip8::log_flags`]
+    fn log_flag(&self, instruction: Instruction, inputs: &[u8]) {
+        if !self.log_flags {
+            return;
+        }
+
+        let (Instruction::AddRegisters { register_x, .. }
+        | Instruction::SubRegisters { register_x, .. }
+        | Instruction::SubRegistersOtherWayArround { register_x, .. }
+        | Instruction::LeftShiftRegister { register_x, .. }
+        | Instruction::RightShiftRegister { register_x, .. }) = instruction
+        else {
+            return;
+        };
+
+        // the subtractions set VF when there is no borrow, which is easy to get backwards
+        let meaning = match (instruction, self.registers[0xF]) {
+            (
+                Instruction::SubRegisters { .. } | Instruction::SubRegistersOtherWayArround { .. },
+                0,
+            ) => " (borrow)",
+            (
+                Instruction::SubRegisters { .. } | Instruction::SubRegistersOtherWayArround { .. },
+                _,
+            ) => " (no borrow)",
+            _ => "",
+        };
+
+        log::info!(
+            target: LOG_TARGET_FLAGS,
+            "0x{:X} {instruction}: inputs {inputs:02X?}, V{register_x:X} = 0x{:02X}, VF = {}{meaning}",
+            self.pc - 2,
+            self.registers[register_x],
+            self.registers[0xF]
+        );
+    }
+
+    fn execute_instruction(
+        &mut self,
+        address: usize,
+        instruction: Instruction,
+    ) -> Result<(), EmuError> {
         match instruction {
             Instruction::Clear => {
+                self.showing_test_pattern = false;
                 self.vram.fill(0);
-                self.redraw = true;
+                self.vram_version = self.vram_version.wrapping_add(1);
             }
 
             Instruction::JumpToAddress { address } => {
@@ -153,58 +548,86 @@ impl Chip8 {
                 register_y,
                 len,
             } => {
-                let start_x: u16 = u16::from(self.registers[register_x]);
-                let start_y: u16 = u16::from(self.registers[register_y]);
-
-                let start_x = if start_x > 0x3F {
-                    start_x % DISPLAY_WIDTH
-                } else {
-                    start_x
+                // the starting position always wraps around, only the pixels that end up
+                // outside of the screen depend on the wrap quirk
+                let start_x: u16 = u16::from(self.registers[register_x]) % DISPLAY_WIDTH;
+                let start_y: u16 = u16::from(self.registers[register_y]) % DISPLAY_HEIGHT;
+
+                // DXY0 draws a 16x16 sprite with two bytes per row on the SCHIP,
+                // the original interpreter draws nothing
+                let (width, bytes_per_row, len) = match len {
+                    0 if self.quirks.large_sprites => (16, 2, 32),
+                    0 => {
+                        log::trace!(target: LOG_TARGET_DRAWING, "ignoring sprite without rows");
+                        return Ok(());
+                    }
+                    len => (8, 1, usize::from(len)),
                 };
-                let start_y = if start_y > 0x1F {
-                    start_y % DISPLAY_HEIGHT
+
+                log::trace!(target: LOG_TARGET_DRAWING, "drawing {len} bytes at {start_x},{start_y}");
+
+                let lo = self.address_register as usize;
+                let hi = lo + len;
+                let sprite = if let Some(sprite) = self.memory.get(lo..hi) {
+                    sprite
+                } else if self.quirks.clamp_sprite_reads {
+                    log::warn!(target: LOG_TARGET_DRAWING, "Sprite at 0x{lo:X} with {len} bytes reaches past the end of memory, only drawing the rows inside of memory");
+                    &self.memory[lo.min(self.memory.len())..]
                 } else {
-                    start_y
+                    return Err(EmuError::MemoryOutOfBounds { addr: lo, len });
                 };
 
-                log::trace!(target: LOG_TARGET_DRAWING, "drawing {len} bytes at {start_x},{start_y}");
+                self.registers[0xF] = 0x00;
 
-                let mut x = start_x;
-                let mut y = start_y;
+                // the ROM expects the screen to be empty
+                if self.showing_test_pattern {
+                    self.showing_test_pattern = false;
+                    self.vram.fill(0);
+                }
 
-                let lo = self.address_register as usize;
-                let hi = lo + len as usize;
-                let sprite = &self.memory[lo..hi];
+                for (y, row) in (start_y..).zip(sprite.chunks(bytes_per_row)) {
+                    let Some(y) = Self::sprite_coordinate(y, DISPLAY_HEIGHT, self.quirks.wrap_y)
+                    else {
+                        break;
+                    };
 
-                assert_eq!(sprite.len(), len as usize);
+                    // a clamped read can end in the middle of a row, the missing byte is empty
+                    let row = row
+                        .iter()
+                        .chain(std::iter::repeat(&0))
+                        .take(bytes_per_row)
+                        .fold(0_u16, |row, byte| row << 8 | u16::from(*byte));
 
-                self.registers[0xF] = 0x00;
+                    for (x, i) in (start_x..).zip((0..width).rev()) {
+                        let Some(x) = Self::sprite_coordinate(x, DISPLAY_WIDTH, self.quirks.wrap_x)
+                        else {
+                            break;
+                        };
 
-                for row in sprite {
-                    for i in (0..8).rev() {
-                        let sprite_pixel = u8::from(row & 2_u8.pow(i) == 2_u8.pow(i));
+                        let sprite_pixel = u8::from(row >> i & 1 == 1);
 
                         if let Some(old_pixel) = get_pixel(&self.vram, x, y) {
-                            let new_pixel = old_pixel ^ sprite_pixel;
+                            let new_pixel = self.quirks.draw_mode.combine(old_pixel, sprite_pixel);
 
                             set_pixel(&mut self.vram, x, y, new_pixel == 1);
 
-                            if old_pixel == 1 && new_pixel == 0 {
+                            if self
+                                .quirks
+                                .collision
+                                .detect(old_pixel, sprite_pixel, new_pixel)
+                            {
                                 self.registers[0xF] = 0x01;
+                                self.sprite_collided = true;
                             }
                         }
-
-                        x += 1;
                     }
-
-                    y += 1;
-                    x = start_x;
                 }
 
                 log::trace!(target:LOG_TARGET_DRAWING, "Finished drawing. VF: {}", self.registers[0xF]);
                 print_vram(&self.vram);
 
-                self.redraw = true;
+                self.vram_version = self.vram_version.wrapping_add(1);
+                self.waiting_for_vblank = self.quirks.display_wait;
 
                 // wait_for_input();
             }
@@ -240,10 +663,14 @@ impl Chip8 {
             }
             Instruction::ExecuteSubroutine { address } => {
                 self.stack.push(self.pc);
+                self.call_stats.record_call(self.stack.len());
                 self.pc = address as usize;
             }
             Instruction::Return => {
-                let address = self.stack.pop().expect("Can't return when stack is empty");
+                let Some(address) = self.stack.pop() else {
+                    return Err(EmuError::StackUnderflow);
+                };
+                self.call_stats.returns += 1;
                 self.pc = address;
             }
             Instruction::CopyRegister {
@@ -283,8 +710,9 @@ impl Chip8 {
                 register_x,
                 register_y,
             } => {
-                let result: u16 =
-                    u16::from(self.registers[register_x]) + u16::from(self.registers[register_y]);
+                let x = self.registers[register_x];
+                let y = self.registers[register_y];
+                let result: u16 = u16::from(x) + u16::from(y);
 
                 let carry = result > u16::from(u8::MAX);
 
@@ -293,6 +721,8 @@ impl Chip8 {
                     self.registers[register_x] = result as u8;
                 }
                 self.registers[0xF] = u8::from(carry);
+
+                self.log_flag(instruction, &[x, y]);
             }
             Instruction::SubRegisters {
                 register_x,
@@ -300,12 +730,15 @@ impl Chip8 {
             } => {
                 let x = self.registers[register_x];
                 let y = self.registers[register_y];
-                let result = x - y;
+                let result = x.wrapping_sub(y);
 
                 self.registers[register_x] = result;
 
+                // VF is the inverted borrow: 1 when nothing was borrowed, including x == y
                 let borrow = y > x;
                 self.registers[0xF] = u8::from(!borrow);
+
+                self.log_flag(instruction, &[x, y]);
             }
             Instruction::SubRegistersOtherWayArround {
                 register_x,
@@ -313,46 +746,65 @@ impl Chip8 {
             } => {
                 let x = self.registers[register_x];
                 let y = self.registers[register_y];
-                let result = y - x;
+                let result = y.wrapping_sub(x);
 
                 self.registers[register_x] = result;
 
+                // VF is the inverted borrow: 1 when nothing was borrowed, including x == y
                 let borrow = x > y;
                 self.registers[0xF] = u8::from(!borrow);
+
+                self.log_flag(instruction, &[x, y]);
             }
             Instruction::LeftShiftRegister {
                 register_x,
                 register_y,
             } => {
-                let value = self.registers[register_y];
+                let value = self.registers[self.shift_source(register_x, register_y)];
                 let vf_temp = value & 0b1000_0000;
 
                 self.registers[register_x] = value << 1;
                 self.registers[0xF] = u8::from(vf_temp == 0b1000_0000);
+
+                self.log_flag(instruction, &[value]);
             }
             Instruction::RightShiftRegister {
                 register_x,
                 register_y,
             } => {
-                let value = self.registers[register_y];
+                let value = self.registers[self.shift_source(register_x, register_y)];
                 let vf_temp = value & 0b0000_0001;
 
                 self.registers[register_x] = value >> 1;
                 self.registers[0xF] = u8::from(vf_temp == 0b0000_0001);
+
+                self.log_flag(instruction, &[value]);
             }
             Instruction::StoreRegisters { register_x } => {
-                for i in 0..=register_x {
-                    self.memory[self.address_register as usize + i] = self.registers[i];
-                }
+                let range = self.address_register_range(register_x + 1)?;
+                self.memory[range.clone()].copy_from_slice(&self.registers[..=register_x]);
+                self.written_memory[range].fill(true);
 
-                self.address_register += u16::try_from(register_x).unwrap() + 1;
+                if self.quirks.increment_i_on_store {
+                    self.add_to_address_register(u16::try_from(register_x).unwrap() + 1)?;
+                }
             }
             Instruction::LoadRegisters { register_x } => {
-                for i in 0..=register_x {
-                    self.registers[i] = self.memory[self.address_register as usize + i];
-                }
+                let range = self.address_register_range(register_x + 1)?;
+                self.registers[..=register_x].copy_from_slice(&self.memory[range]);
 
-                self.address_register += u16::try_from(register_x).unwrap() + 1;
+                if self.quirks.increment_i_on_store {
+                    self.add_to_address_register(u16::try_from(register_x).unwrap() + 1)?;
+                }
+            }
+            Instruction::Unknown(opcode) => {
+                if !self.quirks.skip_unknown_opcodes {
+                    return Err(EmuError::UnknownOpcode {
+                        raw: opcode,
+                        addr: address,
+                    });
+                }
+                log::warn!(target: LOG_TARGET_INSTRUCTIONS, "Skipping the unknown instruction 0x{opcode:04X} at 0x{address:03X}");
             }
             Instruction::BinaryCodedDecimal { register_x } => {
                 let value = self.registers[register_x];
@@ -361,20 +813,25 @@ impl Chip8 {
                 let ten = (value % 100) / 10;
                 let one = value % 10;
 
-                self.memory[self.address_register as usize] = hundred;
-                self.memory[self.address_register as usize + 1] = ten;
-                self.memory[self.address_register as usize + 2] = one;
+                let range = self.address_register_range(3)?;
+                self.memory[range.clone()].copy_from_slice(&[hundred, ten, one]);
+                self.written_memory[range].fill(true);
             }
             Instruction::AddXtoI { register_x } => {
-                self.address_register += u16::from(self.registers[register_x]);
+                self.add_to_address_register(u16::from(self.registers[register_x]))?;
             }
             Instruction::SetDelayTimer { register_x } => {
                 self.delay_timer = self.registers[register_x];
                 log::trace!(target: LOG_TARGET_TIMER, "set delay timer to {}",self.delay_timer);
             }
+            Instruction::SetSoundTimer { register_x } => {
+                self.sound_timer = self.registers[register_x];
+                log::trace!(target: LOG_TARGET_SOUND, "set sound timer to {}", self.sound_timer);
+            }
             Instruction::ReadDelayTimer { register_x } => {
                 self.registers[register_x] = self.delay_timer;
             }
+            // VX can hold any byte, keys > 0xF don't exist and are never down
             Instruction::SkipIfKey { register_x } => {
                 let key = self.registers[register_x];
 
@@ -396,12 +853,24 @@ impl Chip8 {
                 }
             }
             Instruction::WaitForKey { register_x } => {
-                self.mode = Mode::WaitForKey {
-                    register: register_x,
-                };
+                self.waiting_for_key = Some(register_x);
             }
             Instruction::JumpOffsetV0 { address } => {
-                self.pc = (address + u16::from(self.registers[0x00])) as usize;
+                let register = if self.quirks.jump_v0_uses_vx {
+                    usize::from(address >> 8)
+                } else {
+                    0x0
+                };
+                let target = usize::from(address) + usize::from(self.registers[register]);
+
+                // the highest target is 0xFFF + 0xFF, which is outside of 4 KB of memory
+                self.pc = match self.quirks.pc_overflow {
+                    PcOverflow::Wrap => target % self.memory.len(),
+                    PcOverflow::Error | PcOverflow::Halt if target >= self.memory.len() => {
+                        return Err(EmuError::JumpOutOfBounds { target });
+                    }
+                    PcOverflow::Error | PcOverflow::Halt => target,
+                };
             }
             Instruction::LoadFontCharacter { register_x } => {
                 self.address_register = u16::try_from(FONT_START).unwrap()
@@ -409,21 +878,417 @@ impl Chip8 {
                         * u16::from(self.registers[register_x]));
             }
             Instruction::RandomNumber { register_x, mask } => {
-                let r = rand::random::<u8>() & mask;
+                let r = self.rng.next_u8() & mask;
                 self.registers[register_x] = r;
             }
         }
+
+        Ok(())
     }
 
     /// Load and execute the next instruction.
-    /// Returns the instruction.
-    pub fn step_cycle(&mut self) -> anyhow::Result<Instruction> {
+    /// Returns the instruction and whether it changed the screen or collided, so callers don't
+    /// have to compare the state before and after
+    pub fn step_cycle(&mut self) -> Result<StepOutcome, EmuError> {
+        // only decode twice when the history is kept
+        if self.frame_history_len > 0
+            && matches!(
+                self.peek_next(),
+                Ok(Instruction::Clear | Instruction::DrawSprite { .. })
+            )
+        {
+            self.push_frame_history();
+        }
+
+        let address = self.pc;
+        let vram_version = self.vram_version;
         let instruction = self.fetch_and_decode_instruction()?;
+        self.sprite_collided = false;
+        self.execute(address, instruction)?;
+
+        Ok(StepOutcome {
+            instruction,
+            redrew: self.vram_version != vram_version,
+            collision: self.sprite_collided,
+        })
+    }
+
+    /// Count an executed instruction towards the next timer tick, and decrease the delay timer
+    /// every `cycles_per_timer_tick` instructions, see [`cycles_per_timer_tick`].
+    /// Returns true on a tick
+    pub fn count_timer_cycle(&mut self, cycles_per_timer_tick: u32) -> bool {
+        self.timer_cycles += 1;
+        if self.timer_cycles < cycles_per_timer_tick {
+            return false;
+        }
+
+        self.timer_cycles = 0;
+        self.frame_count += 1;
+        self.waiting_for_vblank = false;
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+        log::trace!(target: LOG_TARGET_VBLANK, "timer tick, delay timer: {}, sound timer: {}", self.delay_timer, self.sound_timer);
 
-        self.execute_instruction(instruction);
+        true
+    }
+
+    /// Count one pass of a frontend's interpreter loop towards the next timer tick, see
+    /// [`Chip8::count_timer_cycle`]. `mode` is the mode the pass started in, `executed` whether
+    /// it executed an instruction.
+    ///
+    /// A pass counts exactly once: time keeps passing while no instruction is executed, e.g.
+    /// while `FX0A` waits for a key, but counting an executed instruction again would run the
+    /// timers twice as fast. While paused, only executed single steps count, and only if
+    /// `step_timers` is set. Returns true on a tick
+    pub fn count_loop_cycle(
+        &mut self,
+        mode: Mode,
+        executed: bool,
+        step_timers: bool,
+        cycles_per_timer_tick: u32,
+    ) -> bool {
+        let counts = match mode {
+            Mode::Running | Mode::Halted => true,
+            Mode::Paused => executed && step_timers,
+        };
+
+        counts && self.count_timer_cycle(cycles_per_timer_tick)
+    }
+
+    /// Execute instructions until the next timer tick, counting each one with
+    /// [`Chip8::count_timer_cycle`], so the delay timer is decreased by exactly one. Starting right
+    /// after a tick, that's `cycles_per_timer_tick` instructions. Time passes without executing
+    /// instructions while `FX0A` waits for a key or `DXYN` for the display.
+    /// Returns how many instructions were executed
+    pub fn step_timer_tick(&mut self, cycles_per_timer_tick: u32) -> Result<u32, EmuError> {
+        let mut executed = 0;
+        loop {
+            if self.waiting_for_key.is_none() && !self.waiting_for_vblank {
+                self.step_cycle()?;
+                executed += 1;
+            }
+            if self.count_timer_cycle(cycles_per_timer_tick) {
+                return Ok(executed);
+            }
+        }
+    }
+
+    /// Whether the buzzer should sound, i.e. the sound timer is above 0
+    pub fn is_sound_playing(&self) -> bool {
+        self.sound_timer > 0
+    }
+
+    /// How many subroutines were called and returned from, and how deep the stack is and was.
+    /// See [`CallStats::unbalanced_warning`] to check the end of a run
+    pub fn call_stats(&self) -> CallStats {
+        CallStats {
+            depth: self.stack.len(),
+            // a restored stack can be deeper than any call made since
+            max_depth: self.call_stats.max_depth.max(self.stack.len()),
+            ..self.call_stats
+        }
+    }
+
+    /// How many 60 Hz frames passed, counted by [`Chip8::count_timer_cycle`]. Unlike the number
+    /// of executed instructions, it tells how long a ROM ran at the usual speed, e.g. to stop a
+    /// visual test after a second worth of frames
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Decode and execute a single opcode like [`Chip8::step_cycle`], but without reading it from
+    /// memory at the PC and without advancing the PC. Jumps and skips still change the PC.
+    /// Returns the instruction.
+    ///
+    /// ```
+    /// use chip8stuff::chip8::{instructions::Instruction, Chip8, PC_INIT};
+    ///
+    /// let mut chip8 = Chip8::new().with_register(0x1, 0x05)?;
+    ///
+    /// // ADD V1, 0x03
+    /// let instruction = chip8.execute_raw(0x7103)?;
+    ///
+    /// assert_eq!(instruction, Instruction::AddToRegister { register: 0x1, value: 0x03 });
+    /// assert_eq!(chip8.registers[0x1], 0x08);
+    /// assert_eq!(chip8.pc, PC_INIT);
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn execute_raw(&mut self, opcode: u16) -> Result<Instruction, EmuError> {
+        let instruction = Instruction::try_from(opcode).map_err(|_| EmuError::UnknownOpcode {
+            raw: opcode,
+            addr: self.pc,
+        })?;
+        if matches!(
+            instruction,
+            Instruction::Clear | Instruction::DrawSprite { .. }
+        ) {
+            self.push_frame_history();
+        }
+
+        self.execute(self.pc, instruction)?;
 
         Ok(instruction)
     }
+
+    /// Remember the state before an instruction that changes the screen, see
+    /// [`Chip8::previous_frame`]
+    fn push_frame_history(&mut self) {
+        if self.frame_history_len == 0 {
+            return;
+        }
+
+        self.frame_history.push_back(self.snapshot());
+        while self.frame_history.len() > self.frame_history_len {
+            self.frame_history.pop_front();
+        }
+    }
+
+    /// Execute a decoded instruction with the hook, logging and breakpoints around it.
+    /// `address` is where the instruction came from, only used for logging
+    fn execute(&mut self, address: usize, instruction: Instruction) -> Result<(), EmuError> {
+        // the hook can't be borrowed from self while self is passed to it
+        if let Some(mut hook) = self.pre_execute_hook.take() {
+            hook(self, &instruction);
+            self.pre_execute_hook = Some(hook);
+        }
+
+        let vf = self.registers[0xF];
+        self.execute_instruction(address, instruction)?;
+        if self.log_vf_changes && self.registers[0xF] != vf {
+            log::info!(
+                target: LOG_TARGET_FLAGS,
+                "0x{address:X} {instruction}: VF 0x{vf:02X} -> 0x{:02X}",
+                self.registers[0xF]
+            );
+        }
+
+        self.check_breakpoints();
+
+        Ok(())
+    }
+
+    /// Go back to right before the last instruction that changed the screen, see
+    /// [`Chip8::frame_history_len`]. Going back again restores the draw before that one.
+    /// The mode is kept. Returns false when there is no earlier draw left
+    pub fn previous_frame(&mut self) -> anyhow::Result<bool> {
+        let Some(snapshot) = self.frame_history.pop_back() else {
+            return Ok(false);
+        };
+
+        let mode = self.mode;
+        self.restore(&snapshot)?;
+        self.mode = mode;
+
+        Ok(true)
+    }
+
+    /// Call `hook` before every instruction is executed, e.g. for custom tracing or coverage.
+    /// The PC already points to the following instruction when the hook is called.
+    /// Replaces a previously installed hook
+    pub fn set_pre_execute_hook(&mut self, hook: PreExecuteHook) {
+        self.pre_execute_hook = Some(hook);
+    }
+
+    pub fn remove_pre_execute_hook(&mut self) {
+        self.pre_execute_hook = None;
+    }
+
+    /// Replace where `CXNN` takes its random numbers from, e.g. with a [`rng::FixedRng`] to make
+    /// a test deterministic. A new interpreter uses [`DefaultRng::from_entropy`]
+    pub fn set_rng(&mut self, rng: impl RandomSource + 'static) {
+        self.rng = Box::new(rng);
+    }
+
+    /// Pause before the next instruction is executed when the condition becomes true
+    pub fn add_breakpoint(&mut self, condition: Condition) {
+        if self
+            .breakpoints
+            .iter()
+            .all(|b| b.condition != condition || b.one_shot)
+        {
+            log::trace!(target: LOG_TARGET_DEBUGGER, "adding breakpoint {condition}");
+
+            let breakpoint = Breakpoint::new(condition, self);
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, condition: Condition) {
+        log::trace!(target: LOG_TARGET_DEBUGGER, "removing breakpoint {condition}");
+
+        self.breakpoints.retain(|b| b.condition != condition);
+    }
+
+    /// The conditions of all breakpoints, except the temporary one of [`Chip8::run_to`]
+    pub fn breakpoints(&self) -> impl Iterator<Item = Condition> + '_ {
+        self.breakpoints
+            .iter()
+            .filter(|b| !b.one_shot)
+            .map(|b| b.condition)
+    }
+
+    /// Continue until the PC reaches `address`, then pause. Replaces an earlier target.
+    /// If the PC is at `address` already, it has to come back to it
+    pub fn run_to(&mut self, address: usize) {
+        log::trace!(target: LOG_TARGET_DEBUGGER, "running to 0x{address:X}");
+
+        self.breakpoints.retain(|b| !b.one_shot);
+        let breakpoint = Breakpoint::one_shot(Condition::Pc(address), self);
+        self.breakpoints.push(breakpoint);
+
+        if self.mode == Mode::Paused {
+            self.mode = Mode::Running;
+        }
+    }
+
+    /// Fill the vram with a test pattern to check the rendering before the ROM draws anything:
+    /// a border around the screen and a checkerboard inside.
+    /// The pattern is removed by the first instruction that clears the screen or draws a sprite
+    pub fn fill_test_pattern(&mut self) {
+        for y in 0..DISPLAY_HEIGHT {
+            for x in 0..DISPLAY_WIDTH {
+                let border = x == 0 || y == 0 || x == DISPLAY_WIDTH - 1 || y == DISPLAY_HEIGHT - 1;
+                set_pixel(&mut self.vram, x, y, border || (x + y) % 2 == 0);
+            }
+        }
+
+        self.showing_test_pattern = true;
+        self.vram_version = self.vram_version.wrapping_add(1);
+    }
+
+    /// The screen, one byte per pixel row by row, 1 if the pixel is on
+    pub fn vram(&self) -> &[u8] {
+        &self.vram
+    }
+
+    /// How many pixels are on
+    pub fn pixel_count(&self) -> usize {
+        self.vram.iter().map(|pixel| usize::from(*pixel)).sum()
+    }
+
+    /// Replace the screen, e.g. to start a test from a known screen or to show a saved frame.
+    /// `pixels` has one byte per pixel like [`Chip8::vram`] and has to cover the whole screen
+    pub fn set_vram(&mut self, pixels: &[u8]) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            pixels.len() == self.vram.len(),
+            "The screen has {} pixels, got {}",
+            self.vram.len(),
+            pixels.len()
+        );
+
+        self.vram.copy_from_slice(pixels);
+        // the screen isn't the test pattern anymore, so the next draw must not clear it
+        self.showing_test_pattern = false;
+        self.vram_version = self.vram_version.wrapping_add(1);
+
+        Ok(())
+    }
+
+    /// Set the state of all keys at once, bit n is set when key n is down.
+    /// Useful to feed input from somewhere else than the window, e.g. to replay recorded input
+    pub fn set_keys(&mut self, mask: u16) {
+        self.keyboard = Keyboard(mask);
+    }
+
+    /// The state of all keys, bit n is set when key n is down
+    pub fn keys(&self) -> u16 {
+        self.keyboard.0
+    }
+
+    /// Remember that a key went down or up, in the order the input arrived.
+    /// Nothing changes until [`Chip8::process_key_events`] is called
+    pub fn queue_key_event(&mut self, key: u8, down: bool) {
+        self.key_events.push_back(KeyEvent { key, down });
+    }
+
+    /// Queue an event for every key that differs between two key masks, see [`Chip8::keys`].
+    /// Lets frontends merge several input sources into one mask and only report what changed
+    pub fn queue_key_changes(&mut self, before: u16, after: u16) {
+        for key in 0..16_u8 {
+            let bit = 1 << key;
+            if before & bit != after & bit {
+                self.queue_key_event(key, after & bit != 0);
+            }
+        }
+    }
+
+    /// Apply the queued key events to the keyboard, oldest first. While waiting for a key
+    /// (`FX0A`), the first release of a key that was down ends the wait, no matter how many keys
+    /// changed since the last call. Releases of keys that were not down are ignored
+    pub fn process_key_events(&mut self) {
+        while let Some(KeyEvent { key, down }) = self.key_events.pop_front() {
+            if down {
+                self.keyboard.set_down(key);
+                continue;
+            }
+
+            if !self.keyboard.is_down(key) {
+                continue;
+            }
+            self.keyboard.set_up(key);
+
+            if let Some(register) = self.waiting_for_key.take() {
+                self.registers[register] = key;
+            }
+        }
+    }
+
+    /// A fingerprint of the registers, PC, I, delay and sound timer, stack and screen.
+    /// Equal states have equal hashes, so the same hash after an instruction means the ROM is stuck
+    /// in a loop that does nothing. Memory, keys and quirks are not included.
+    /// The hash is only stable for the same build of the interpreter
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        self.registers.hash(&mut hasher);
+        self.pc.hash(&mut hasher);
+        self.address_register.hash(&mut hasher);
+        self.delay_timer.hash(&mut hasher);
+        self.sound_timer.hash(&mut hasher);
+        self.stack.hash(&mut hasher);
+
+        // one bit per pixel
+        for pixels in self.vram.chunks(8) {
+            let packed = pixels
+                .iter()
+                .fold(0_u8, |byte, pixel| (byte << 1) | (pixel & 1));
+            packed.hash(&mut hasher);
+        }
+
+        hasher.finish()
+    }
+
+    fn check_breakpoints(&mut self) {
+        let mut breakpoints = std::mem::take(&mut self.breakpoints);
+
+        breakpoints.retain_mut(|breakpoint| {
+            if !breakpoint.update(self) {
+                return true;
+            }
+
+            log::info!(target: LOG_TARGET_DEBUGGER, "Breakpoint hit: {}", breakpoint.condition);
+
+            // don't forget about a key we are waiting for
+            if self.mode == Mode::Running {
+                self.mode = Mode::Paused;
+            }
+
+            !breakpoint.one_shot
+        });
+
+        self.breakpoints = breakpoints;
+    }
+}
+
+/// Memory has to be larger than the interpreter area in front of [`PC_INIT`], and addressable by I
+fn check_memory_size(size: usize) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        (PC_INIT + 1..=MAX_MEMORY_SIZE).contains(&size),
+        "Memory size of {size} bytes is not supported, expected 0x{:X} to 0x{MAX_MEMORY_SIZE:X} bytes",
+        PC_INIT + 1
+    );
+
+    Ok(())
 }
 
 /// Convert x and y coordinates to a linear index
@@ -450,6 +1315,101 @@ fn get_pixel(vram: &[u8], x: u16, y: u16) -> Option<u8> {
     vram_index(x, y).map(|index| vram[index])
 }
 
+/// Render the vram as text, one line per row. Pixels that are on are shown as `#`
+pub fn vram_rows(vram: &[u8]) -> Vec<String> {
+    (0..DISPLAY_HEIGHT)
+        .map(|y| {
+            (0..DISPLAY_WIDTH)
+                .map(|x| match get_pixel(vram, x, y) {
+                    Some(1) => '#',
+                    _ => '.',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Turn text art into sprite bytes, the inverse of [`vram_rows`]: one line per row, `#` for pixels
+/// that are on and `.` for pixels that are off. Rows are 8 pixels wide for `DXYN`, or 16 pixels
+/// for the 16x16 sprites of `DXY0`, which take two bytes per row. Leading and trailing whitespace
+/// and empty lines are ignored, so the art can be indented in source code
+///
+/// ```
+/// use chip8stuff::chip8::sprite_from_art;
+///
+/// let sprite = sprite_from_art(
+///     "
+///     .####...
+///     #....#..
+///     ",
+/// )?;
+///
+/// assert_eq!(sprite, [0x78, 0x84]);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn sprite_from_art(art: &str) -> anyhow::Result<Vec<u8>> {
+    let rows: Vec<&str> = art
+        .lines()
+        .map(str::trim)
+        .filter(|row| !row.is_empty())
+        .collect();
+
+    let Some(width) = rows.first().map(|row| row.chars().count()) else {
+        anyhow::bail!("The sprite has no rows");
+    };
+    anyhow::ensure!(
+        width == 8 || width == 16,
+        "Sprite rows have to be 8 or 16 pixels wide, the first one has {width}"
+    );
+
+    let mut bytes = Vec::with_capacity(rows.len() * width / 8);
+    for (i, row) in rows.iter().enumerate() {
+        let pixels = row
+            .chars()
+            .map(|c| match c {
+                '#' => Ok(true),
+                '.' => Ok(false),
+                _ => Err(anyhow::anyhow!(
+                    "Unexpected {c:?} in sprite row {i}, only '#' and '.' are allowed"
+                )),
+            })
+            .collect::<anyhow::Result<Vec<bool>>>()?;
+        anyhow::ensure!(
+            pixels.len() == width,
+            "Sprite row {i} is {} pixels wide, but the first one has {width}",
+            pixels.len()
+        );
+
+        bytes.extend(
+            pixels
+                .chunks(8)
+                .map(|byte| byte.iter().fold(0, |byte, on| byte << 1 | u8::from(*on))),
+        );
+    }
+
+    Ok(bytes)
+}
+
+/// Pack a rectangle of the vram into sprite data the way `DXYN` reads it: one byte per row with
+/// the leftmost pixel in the highest bit. Rectangles wider than 8 pixels are split into columns of
+/// 8 pixels, returned from left to right. Pixels right of the rectangle or outside of the screen
+/// are off
+pub fn vram_sprites(vram: &[u8], x: u16, y: u16, width: u16, height: u16) -> Vec<Vec<u8>> {
+    (0..width.div_ceil(8))
+        .map(|column| {
+            (y..y + height)
+                .map(|row| {
+                    (0..8).fold(0, |byte, bit| {
+                        let on = column * 8 + bit < width
+                            && get_pixel(vram, x + column * 8 + bit, row) == Some(1);
+                        byte << 1 | u8::from(on)
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
 fn print_vram(vram: &[u8]) {
     let mut s = String::new();
 
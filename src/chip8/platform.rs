@@ -0,0 +1,115 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use super::{quirks::Quirks, DEFAULT_MEMORY_SIZE, MAX_MEMORY_SIZE};
+
+/// The interpreter a ROM was written for. Apart from 16x16 sprites, only the CHIP-8 instructions
+/// are implemented, but choosing the platform selects the quirks that its ROMs expect
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    /// the original COSMAC VIP interpreter
+    #[default]
+    Chip8,
+    /// SUPER-CHIP 1.1 on the HP48 calculators
+    #[serde(rename = "schip")]
+    SuperChip,
+    /// the XO-CHIP extension of the SUPER-CHIP
+    XoChip,
+}
+
+impl Platform {
+    /// Guess the platform by looking for opcodes that only exist on the SUPER-CHIP or XO-CHIP.
+    ///
+    /// The whole ROM is scanned, including sprites and other data, so a ROM can be mistaken
+    /// for a newer platform if its data happens to look like one of these opcodes.
+    pub fn detect(rom: &[u8]) -> Platform {
+        let opcodes = rom
+            .chunks_exact(2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]));
+
+        let mut platform = Platform::Chip8;
+        for opcode in opcodes {
+            if is_xo_chip_opcode(opcode) {
+                return Platform::XoChip;
+            }
+            if is_super_chip_opcode(opcode) {
+                platform = Platform::SuperChip;
+            }
+        }
+
+        platform
+    }
+
+    /// How much memory the platform has
+    pub fn memory_size(self) -> usize {
+        match self {
+            Platform::Chip8 | Platform::SuperChip => DEFAULT_MEMORY_SIZE,
+            Platform::XoChip => MAX_MEMORY_SIZE,
+        }
+    }
+
+    /// The quirks that ROMs for this platform usually expect
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks::default(),
+            Platform::SuperChip => Quirks {
+                shift_uses_vy: false,
+                increment_i_on_store: false,
+                large_sprites: true,
+                jump_v0_uses_vx: true,
+                ..Quirks::default()
+            },
+            Platform::XoChip => Quirks {
+                wrap_x: true,
+                wrap_y: true,
+                large_sprites: true,
+                ..Quirks::default()
+            },
+        }
+    }
+}
+
+/// `00CN` scroll down, `00FB`/`00FC` scroll right/left, `00FD` exit, `00FE`/`00FF` low/high
+/// resolution, `DXY0` 16x16 sprites, `FX30` large font and `FX75`/`FX85` flag registers
+fn is_super_chip_opcode(opcode: u16) -> bool {
+    opcode & 0xFFF0 == 0x00C0
+        || matches!(opcode, 0x00FB..=0x00FF)
+        || opcode & 0xF00F == 0xD000
+        || matches!(opcode & 0xF0FF, 0xF030 | 0xF075 | 0xF085)
+}
+
+/// `00DN` scroll up, `5XY2`/`5XY3` register ranges, `F000 NNNN` long I, `FN01` plane selection,
+/// `F002` audio pattern and `FX3A` pitch
+fn is_xo_chip_opcode(opcode: u16) -> bool {
+    opcode & 0xFFF0 == 0x00D0
+        || matches!(opcode & 0xF00F, 0x5002 | 0x5003)
+        || matches!(opcode, 0xF000 | 0xF002)
+        || matches!(opcode & 0xF0FF, 0xF001 | 0xF03A)
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Platform::Chip8 => write!(f, "chip8"),
+            Platform::SuperChip => write!(f, "schip"),
+            Platform::XoChip => write!(f, "xochip"),
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "chip8" => Ok(Platform::Chip8),
+            "schip" => Ok(Platform::SuperChip),
+            "xochip" => Ok(Platform::XoChip),
+            _ => Err(anyhow::anyhow!(
+                "unknown platform {s}, expected chip8, schip or xochip"
+            )),
+        }
+    }
+}
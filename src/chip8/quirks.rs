@@ -0,0 +1,193 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Behaviour that differs between CHIP-8 interpreters, or is only useful for debugging
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Quirks {
+    /// how sprites are combined with the screen content
+    pub draw_mode: DrawMode,
+    /// when DXYN sets VF. Both rules are the same for XOR drawing
+    pub collision: Collision,
+    /// whether 8XY6 and 8XYE shift VY and store the result in VX, like the original interpreter.
+    /// Otherwise VX is shifted in place and VY is ignored, like on the SCHIP
+    pub shift_uses_vy: bool,
+    /// whether sprite pixels that are drawn past the right edge wrap around to the left edge.
+    /// Otherwise they are clipped. The starting position of a sprite wraps around in both cases
+    pub wrap_x: bool,
+    /// whether sprite pixels that are drawn past the bottom edge wrap around to the top edge,
+    /// independent of [`Quirks::wrap_x`]
+    pub wrap_y: bool,
+    /// whether FX55 and FX65 leave I pointing behind the last stored or loaded register,
+    /// like the original interpreter. Otherwise I is left unchanged, like on the SCHIP
+    pub increment_i_on_store: bool,
+    /// whether DXYN draws the rows that are inside of memory when the sprite reaches past the end
+    /// of memory, like some lenient interpreters. Otherwise the instruction fails
+    pub clamp_sprite_reads: bool,
+    /// whether words that are no instruction are skipped with a warning, for ROMs that step
+    /// through data before a jump. Otherwise the instruction fails
+    pub skip_unknown_opcodes: bool,
+    /// whether DXY0 draws a 16x16 sprite, like the SCHIP. Otherwise it draws nothing
+    pub large_sprites: bool,
+    /// whether I wraps around at 0xFFFF when FX1E, FX55 or FX65 add to it.
+    /// Otherwise the instruction fails if I would point outside of memory
+    pub wrap_address_register: bool,
+    /// what happens when the PC runs off the end of memory
+    pub pc_overflow: PcOverflow,
+    /// whether BNNN is read as BXNN and adds VX to the address, where X is the highest digit of
+    /// the address, like on the SCHIP. Otherwise V0 is added, like on the original interpreter
+    pub jump_v0_uses_vx: bool,
+    /// whether DXYN waits for the next timer tick before the next instruction is executed, like
+    /// the original interpreter waited for the vertical blank. Limits ROMs to one sprite per
+    /// frame, which reduces flicker. Only delays instructions, so it can be changed at any time
+    pub display_wait: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks {
+            draw_mode: DrawMode::default(),
+            collision: Collision::default(),
+            shift_uses_vy: true,
+            wrap_x: false,
+            wrap_y: false,
+            increment_i_on_store: true,
+            clamp_sprite_reads: false,
+            skip_unknown_opcodes: false,
+            large_sprites: false,
+            wrap_address_register: true,
+            pc_overflow: PcOverflow::default(),
+            jump_v0_uses_vx: false,
+            display_wait: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DrawMode {
+    /// the standard CHIP-8 behaviour, drawing a pixel twice erases it
+    #[default]
+    Xor,
+    /// pixels are never erased, which helps to see what a ROM intends to draw
+    Or,
+    /// only pixels that are set in both the sprite and on the screen are kept
+    And,
+}
+
+impl DrawMode {
+    /// Combine a pixel on the screen with a sprite pixel
+    pub fn combine(self, screen_pixel: u8, sprite_pixel: u8) -> u8 {
+        match self {
+            DrawMode::Xor => screen_pixel ^ sprite_pixel,
+            DrawMode::Or => screen_pixel | sprite_pixel,
+            DrawMode::And => screen_pixel & sprite_pixel,
+        }
+    }
+}
+
+/// The rule that decides whether drawing a sprite pixel is a collision that sets VF
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Collision {
+    /// a pixel that was on is turned off, like on the original interpreter
+    #[default]
+    Erased,
+    /// a sprite pixel is drawn over a pixel that is on, no matter what the result is
+    Overlap,
+}
+
+impl Collision {
+    /// Whether drawing `sprite_pixel` over `screen_pixel`, resulting in `new_pixel`, is a collision
+    pub fn detect(self, screen_pixel: u8, sprite_pixel: u8, new_pixel: u8) -> bool {
+        match self {
+            Collision::Erased => screen_pixel == 1 && new_pixel == 0,
+            Collision::Overlap => screen_pixel == 1 && sprite_pixel == 1,
+        }
+    }
+}
+
+impl Display for Collision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Collision::Erased => write!(f, "erased"),
+            Collision::Overlap => write!(f, "overlap"),
+        }
+    }
+}
+
+impl FromStr for Collision {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "erased" => Ok(Collision::Erased),
+            "overlap" => Ok(Collision::Overlap),
+            _ => Err(anyhow::anyhow!(
+                "unknown collision rule {s}, expected erased or overlap"
+            )),
+        }
+    }
+}
+
+/// What happens when the next instruction would be read from outside of memory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PcOverflow {
+    /// the instruction fails, the debugger pauses so the state can be inspected
+    #[default]
+    Error,
+    /// the interpreter stops for good, like a machine that crashed
+    Halt,
+    /// the PC wraps around to the start of memory
+    Wrap,
+}
+
+impl Display for PcOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PcOverflow::Error => write!(f, "error"),
+            PcOverflow::Halt => write!(f, "halt"),
+            PcOverflow::Wrap => write!(f, "wrap"),
+        }
+    }
+}
+
+impl FromStr for PcOverflow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(PcOverflow::Error),
+            "halt" => Ok(PcOverflow::Halt),
+            "wrap" => Ok(PcOverflow::Wrap),
+            _ => Err(anyhow::anyhow!(
+                "unknown PC overflow behaviour {s}, expected error, halt or wrap"
+            )),
+        }
+    }
+}
+
+impl Display for DrawMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrawMode::Xor => write!(f, "xor"),
+            DrawMode::Or => write!(f, "or"),
+            DrawMode::And => write!(f, "and"),
+        }
+    }
+}
+
+impl FromStr for DrawMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "xor" => Ok(DrawMode::Xor),
+            "or" => Ok(DrawMode::Or),
+            "and" => Ok(DrawMode::And),
+            _ => Err(anyhow::anyhow!(
+                "unknown draw mode {s}, expected xor, or or and"
+            )),
+        }
+    }
+}
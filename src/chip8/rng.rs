@@ -0,0 +1,52 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Where `CXNN` takes its random numbers from, see [`super::Chip8::set_rng`]. Implement it to
+/// replay a recorded sequence or to use another generator
+pub trait RandomSource: Send {
+    fn next_u8(&mut self) -> u8;
+}
+
+/// The random source of a new interpreter, backed by [`StdRng`]
+pub struct DefaultRng(StdRng);
+
+impl DefaultRng {
+    /// Seeded from the operating system, so every run differs
+    pub fn from_entropy() -> Self {
+        DefaultRng(StdRng::from_entropy())
+    }
+
+    /// The same seed gives the same numbers, but only for the same version of the `rand` crate.
+    /// Use [`FixedRng`] for numbers that never change
+    pub fn seeded(seed: u64) -> Self {
+        DefaultRng(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl RandomSource for DefaultRng {
+    fn next_u8(&mut self) -> u8 {
+        self.0.gen()
+    }
+}
+
+/// Returns the given bytes in order and starts over after the last one, for tests that need
+/// `CXNN` to be deterministic. Returns 0 if no bytes are given
+pub struct FixedRng {
+    bytes: Vec<u8>,
+    next: usize,
+}
+
+impl FixedRng {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        FixedRng { bytes, next: 0 }
+    }
+}
+
+impl RandomSource for FixedRng {
+    fn next_u8(&mut self) -> u8 {
+        let Some(byte) = self.bytes.get(self.next) else {
+            return 0;
+        };
+        self.next = (self.next + 1) % self.bytes.len();
+        *byte
+    }
+}
@@ -0,0 +1,54 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// Reversed polynomial of the CRC-32 used by zip and PNG
+const CRC32_POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// What was loaded by [`super::Chip8::load_rom`] or [`super::Chip8::load_rom_bytes`], to tell
+/// which ROM is running, e.g. in bug reports
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RomInfo {
+    /// file name, None for ROMs loaded from bytes
+    pub name: Option<String>,
+    /// size in bytes
+    pub len: usize,
+    pub crc32: u32,
+}
+
+impl RomInfo {
+    pub fn new(rom: &[u8]) -> Self {
+        RomInfo {
+            name: None,
+            len: rom.len(),
+            crc32: crc32(rom),
+        }
+    }
+}
+
+impl Display for RomInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({} bytes, CRC32 {:08X})",
+            self.name.as_deref().unwrap_or("unnamed ROM"),
+            self.len,
+            self.crc32
+        )
+    }
+}
+
+/// CRC-32 as used by zip and PNG, so it can be compared with the output of common tools
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (CRC32_POLYNOMIAL & mask);
+        }
+    }
+
+    !crc
+}
@@ -0,0 +1,152 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use super::{cycles_per_timer_tick, quirks::Quirks, Chip8, Mode, DELAY_TIMER_FREQUENCY};
+
+/// The speed the delay timer is decreased at is based on, in instructions per second
+pub const INSTRUCTION_FREQUENCY: f32 = 800.0;
+
+/// Why [`run_rom`] or [`run`] stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopReason {
+    /// an instruction didn't change anything, e.g. the jump to itself most ROMs end with
+    Idle,
+    /// the PC ran off the end of memory, see [`super::quirks::PcOverflow::Halt`]
+    Halted,
+    /// `FX0A` waits for a key, but nobody is there to press one
+    WaitingForKey,
+    /// an instruction failed, e.g. an unknown opcode or a return without a call
+    Error(String),
+    /// `max_cycles` instructions were executed
+    CycleLimit,
+    /// the ROM changed the screen [`Limits::until_frame`] times
+    UntilFrame,
+    /// [`Limits::frames`] timer ticks passed
+    Frames,
+}
+
+/// When [`run`] stops at the latest, whichever limit is reached first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// how many instructions to execute at most
+    pub max_cycles: usize,
+    /// stop as soon as the ROM changed the screen N times, e.g. 1 for the first frame it draws.
+    /// Clearing the screen counts, too
+    pub until_frame: Option<u64>,
+    /// stop after N timer ticks, e.g. 60 for a second
+    pub frames: Option<u64>,
+}
+
+impl Limits {
+    /// Only stop after `max_cycles` instructions
+    pub fn cycles(max_cycles: usize) -> Self {
+        Limits {
+            max_cycles,
+            until_frame: None,
+            frames: None,
+        }
+    }
+}
+
+/// The state after [`run_rom`] or [`run`] stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunResult {
+    pub registers: [u8; 16],
+    /// compare it with the hash of a known good run to check what the ROM drew.
+    /// Only stable for the same build of the interpreter
+    pub vram_hash: u64,
+    /// how many instructions were executed, including a failed one
+    pub cycles: usize,
+    /// how many timer ticks passed, see [`Chip8::frame_count`]
+    pub frames: u64,
+    pub stop_reason: StopReason,
+}
+
+/// Load a ROM and run it without a frontend until it stops by itself or `max_cycles` instructions
+/// were executed. The delay timer is decreased as if the ROM ran at the usual speed.
+///
+/// Only loading the ROM returns an error, failed instructions end up in
+/// [`RunResult::stop_reason`]. Useful for "does this ROM run" smoke tests:
+///
+/// ```
+/// use chip8stuff::chip8::{
+///     quirks::Quirks,
+///     run::{run_rom, StopReason},
+/// };
+///
+/// // LD V0, 0x05 followed by JP 0x202, a jump to itself
+/// let result = run_rom(&[0x60, 0x05, 0x12, 0x02], 100, Quirks::default())?;
+///
+/// assert_eq!(result.registers[0x0], 0x05);
+/// assert_eq!(result.cycles, 2);
+/// assert_eq!(result.stop_reason, StopReason::Idle);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn run_rom(bytes: &[u8], max_cycles: usize, quirks: Quirks) -> anyhow::Result<RunResult> {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom_bytes(bytes)?;
+    chip8.quirks = quirks;
+
+    Ok(run(&mut chip8, Limits::cycles(max_cycles)))
+}
+
+/// Run an already loaded interpreter without a frontend until it stops by itself or one of the
+/// `limits` is reached, with the same timing as [`run_rom`]
+pub fn run(chip8: &mut Chip8, limits: Limits) -> RunResult {
+    let cycles_per_timer_tick = cycles_per_timer_tick(INSTRUCTION_FREQUENCY, DELAY_TIMER_FREQUENCY);
+
+    let mut cycles = 0;
+    // how often the ROM changed the screen
+    let mut redraws = 0;
+    let stop_reason = loop {
+        if cycles == limits.max_cycles {
+            break StopReason::CycleLimit;
+        }
+        if chip8.waiting_for_key.is_some() {
+            break StopReason::WaitingForKey;
+        }
+
+        // the wait for the display still lets time pass
+        if chip8.waiting_for_vblank {
+            chip8.count_timer_cycle(cycles_per_timer_tick);
+            continue;
+        }
+
+        let hash = chip8.state_hash();
+        let result = chip8.step_cycle();
+        cycles += 1;
+
+        match result {
+            Err(_) if chip8.mode == Mode::Halted => break StopReason::Halted,
+            Err(e) => break StopReason::Error(e.to_string()),
+            Ok(_) if chip8.state_hash() == hash => break StopReason::Idle,
+            Ok(outcome) if outcome.redrew => redraws += 1,
+            Ok(_) => {}
+        }
+
+        chip8.count_timer_cycle(cycles_per_timer_tick);
+
+        if limits.until_frame.is_some_and(|frame| redraws >= frame) {
+            break StopReason::UntilFrame;
+        }
+        if limits
+            .frames
+            .is_some_and(|frames| chip8.frame_count() >= frames)
+        {
+            break StopReason::Frames;
+        }
+    };
+
+    let mut hasher = DefaultHasher::new();
+    chip8.vram.hash(&mut hasher);
+
+    RunResult {
+        registers: chip8.registers,
+        vram_hash: hasher.finish(),
+        cycles,
+        frames: chip8.frame_count(),
+        stop_reason,
+    }
+}
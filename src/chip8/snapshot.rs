@@ -0,0 +1,275 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Chip8, Mode};
+
+/// Serializable copy of the interpreter state
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Snapshot {
+    pub memory: Vec<u8>,
+    pub registers: [u8; 16],
+    pub pc: usize,
+    pub address_register: u16,
+    pub vram: Vec<u8>,
+    pub stack: Vec<usize>,
+    pub delay_timer: u8,
+    #[serde(default)]
+    pub sound_timer: u8,
+    /// the phase of the timers, see [`Chip8::timer_cycles`]
+    #[serde(default)]
+    pub timer_cycles: u32,
+    pub mode: Mode,
+    #[serde(default)]
+    pub waiting_for_key: Option<usize>,
+    /// see [`Chip8::waiting_for_vblank`]
+    #[serde(default)]
+    pub waiting_for_vblank: bool,
+}
+
+/// Something that differs between two snapshots, see [`Snapshot::diff`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Register {
+        register: usize,
+        before: u8,
+        after: u8,
+    },
+    Pc {
+        before: usize,
+        after: usize,
+    },
+    AddressRegister {
+        before: u16,
+        after: u16,
+    },
+    DelayTimer {
+        before: u8,
+        after: u8,
+    },
+    SoundTimer {
+        before: u8,
+        after: u8,
+    },
+    Memory {
+        address: usize,
+        before: u8,
+        after: u8,
+    },
+    Stack {
+        before: Vec<usize>,
+        after: Vec<usize>,
+    },
+    /// how many pixels were turned on or off
+    Vram {
+        pixels: usize,
+    },
+    Mode {
+        before: Mode,
+        after: Mode,
+    },
+    /// the register `FX0A` stores the key in, if waiting
+    WaitingForKey {
+        before: Option<usize>,
+        after: Option<usize>,
+    },
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Register {
+                register,
+                before,
+                after,
+            } => write!(f, "V{register:X}: 0x{before:02X} -> 0x{after:02X}"),
+            Change::Pc { before, after } => write!(f, "PC: 0x{before:03X} -> 0x{after:03X}"),
+            Change::AddressRegister { before, after } => {
+                write!(f, "I: 0x{before:03X} -> 0x{after:03X}")
+            }
+            Change::DelayTimer { before, after } => write!(f, "DT: {before} -> {after}"),
+            Change::SoundTimer { before, after } => write!(f, "ST: {before} -> {after}"),
+            Change::Memory {
+                address,
+                before,
+                after,
+            } => write!(f, "[0x{address:03X}]: 0x{before:02X} -> 0x{after:02X}"),
+            Change::Stack { before, after } => write!(f, "Stack: {before:X?} -> {after:X?}"),
+            Change::Vram { pixels } => write!(f, "{pixels} pixels changed"),
+            Change::Mode { before, after } => write!(f, "Mode: {before:?} -> {after:?}"),
+            Change::WaitingForKey { before, after } => {
+                let waiting = |register: &Option<usize>| match register {
+                    Some(register) => format!("V{register:X}"),
+                    None => "no".to_owned(),
+                };
+                write!(
+                    f,
+                    "Waiting for key: {} -> {}",
+                    waiting(before),
+                    waiting(after)
+                )
+            }
+        }
+    }
+}
+
+impl Snapshot {
+    /// Everything that changed from this snapshot to `after`, e.g. by executing an instruction
+    pub fn diff(&self, after: &Snapshot) -> Vec<Change> {
+        let mut changes = Vec::new();
+
+        if self.pc != after.pc {
+            changes.push(Change::Pc {
+                before: self.pc,
+                after: after.pc,
+            });
+        }
+
+        for (register, (before, after)) in self.registers.iter().zip(after.registers).enumerate() {
+            if *before != after {
+                changes.push(Change::Register {
+                    register,
+                    before: *before,
+                    after,
+                });
+            }
+        }
+
+        if self.address_register != after.address_register {
+            changes.push(Change::AddressRegister {
+                before: self.address_register,
+                after: after.address_register,
+            });
+        }
+
+        if self.delay_timer != after.delay_timer {
+            changes.push(Change::DelayTimer {
+                before: self.delay_timer,
+                after: after.delay_timer,
+            });
+        }
+
+        if self.sound_timer != after.sound_timer {
+            changes.push(Change::SoundTimer {
+                before: self.sound_timer,
+                after: after.sound_timer,
+            });
+        }
+
+        if self.stack != after.stack {
+            changes.push(Change::Stack {
+                before: self.stack.clone(),
+                after: after.stack.clone(),
+            });
+        }
+
+        for (address, (before, after)) in self.memory.iter().zip(&after.memory).enumerate() {
+            if before != after {
+                changes.push(Change::Memory {
+                    address,
+                    before: *before,
+                    after: *after,
+                });
+            }
+        }
+
+        let pixels = self
+            .vram
+            .iter()
+            .zip(&after.vram)
+            .filter(|(before, after)| before != after)
+            .count();
+        if pixels > 0 {
+            changes.push(Change::Vram { pixels });
+        }
+
+        if self.mode != after.mode {
+            changes.push(Change::Mode {
+                before: self.mode,
+                after: after.mode,
+            });
+        }
+
+        if self.waiting_for_key != after.waiting_for_key {
+            changes.push(Change::WaitingForKey {
+                before: self.waiting_for_key,
+                after: after.waiting_for_key,
+            });
+        }
+
+        changes
+    }
+}
+
+impl Chip8 {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            memory: self.memory.clone(),
+            registers: self.registers,
+            pc: self.pc,
+            address_register: self.address_register,
+            vram: self.vram.to_vec(),
+            stack: self.stack.clone(),
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            timer_cycles: self.timer_cycles,
+            mode: self.mode,
+            waiting_for_key: self.waiting_for_key,
+            waiting_for_vblank: self.waiting_for_vblank,
+        }
+    }
+
+    /// Continue from a previously taken snapshot
+    pub fn restore(&mut self, snapshot: &Snapshot) -> anyhow::Result<()> {
+        super::check_memory_size(snapshot.memory.len())?;
+        anyhow::ensure!(
+            snapshot.vram.len() == self.vram.len(),
+            "Snapshot has {} bytes of vram, expected {}",
+            snapshot.vram.len(),
+            self.vram.len()
+        );
+        // a snapshot read from a file can contain anything, nothing may point outside of the
+        // registers or memory
+        anyhow::ensure!(
+            snapshot.pc < snapshot.memory.len(),
+            "Snapshot PC 0x{:X} is outside of memory",
+            snapshot.pc
+        );
+        if let Some(address) = snapshot
+            .stack
+            .iter()
+            .find(|address| **address >= snapshot.memory.len())
+        {
+            anyhow::bail!("Snapshot stack contains 0x{address:X}, which is outside of memory");
+        }
+        if let Some(register) = snapshot
+            .waiting_for_key
+            .filter(|register| *register >= snapshot.registers.len())
+        {
+            anyhow::bail!(
+                "Snapshot waits for a key to store in V{register:X}, which doesn't exist"
+            );
+        }
+
+        // the memory size depends on the platform the snapshot was taken with
+        self.memory.clone_from(&snapshot.memory);
+        // snapshots don't know what was written
+        self.written_memory.clear();
+        self.written_memory.resize(snapshot.memory.len(), false);
+        self.registers = snapshot.registers;
+        self.pc = snapshot.pc;
+        self.address_register = snapshot.address_register;
+        self.vram.copy_from_slice(&snapshot.vram);
+        self.stack.clone_from(&snapshot.stack);
+        self.delay_timer = snapshot.delay_timer;
+        self.sound_timer = snapshot.sound_timer;
+        self.timer_cycles = snapshot.timer_cycles;
+        self.mode = snapshot.mode;
+        self.waiting_for_key = snapshot.waiting_for_key;
+        self.waiting_for_vblank = snapshot.waiting_for_vblank;
+        self.showing_test_pattern = false;
+        self.vram_version = self.vram_version.wrapping_add(1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+use crate::chip8::{snapshot::Change, Chip8};
+
+/// A second interpreter that runs in lockstep with the main one, to find the first instruction
+/// after which two ROM versions or two sets of quirks behave differently, see `--compare`
+pub struct Compare {
+    pub chip8: Chip8,
+    /// instructions executed in lockstep so far
+    pub cycles: u64,
+    pub divergence: Option<Divergence>,
+}
+
+/// Where the compared interpreters went apart
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    /// the instruction after which the states differed first, counted from 1
+    pub cycle: u64,
+    /// from the main interpreter to the compared one
+    pub changes: Vec<Change>,
+    /// the compared interpreter failed to execute its instruction
+    pub error: Option<String>,
+}
+
+impl Compare {
+    pub fn new(chip8: Chip8) -> Self {
+        Compare {
+            chip8,
+            cycles: 0,
+            divergence: None,
+        }
+    }
+
+    /// Copy what the frontend changes outside of instructions: the keys, the timers and the
+    /// key that ended a wait. Call it before the main interpreter executes its next instruction
+    pub fn sync_input(&mut self, main: &Chip8) {
+        if self.divergence.is_some() {
+            return;
+        }
+
+        self.chip8.set_keys(main.keys());
+        self.chip8.delay_timer = main.delay_timer;
+        self.chip8.sound_timer = main.sound_timer;
+
+        // both waited for the same register, until now
+        if main.waiting_for_key.is_none() {
+            if let Some(register) = self.chip8.waiting_for_key.take() {
+                self.chip8.registers[register] = main.registers[register];
+            }
+        }
+    }
+
+    /// Execute the next instruction after the main interpreter executed one, and compare the
+    /// states. Returns true if they differ for the first time
+    pub fn step(&mut self, main: &Chip8) -> bool {
+        if self.divergence.is_some() {
+            return false;
+        }
+
+        self.cycles += 1;
+        let error = match self.chip8.step_cycle() {
+            Ok(_) => None,
+            Err(e) => Some(e.to_string()),
+        };
+
+        if error.is_none() && self.chip8.state_hash() == main.state_hash() {
+            return false;
+        }
+
+        self.divergence = Some(Divergence {
+            cycle: self.cycles,
+            changes: main.snapshot().diff(&self.chip8.snapshot()),
+            error,
+        });
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::instructions::{assemble, Instruction};
+
+    /// Two interpreters with different shift quirks stay in lockstep through `FX0A` and differ
+    /// right after the shift
+    #[test]
+    fn shift_quirk_divergence() {
+        let rom = assemble([
+            Instruction::WaitForKey { register_x: 0x0 },
+            Instruction::StoreNumberInRegister {
+                number: 0x02,
+                register: 0x1,
+            },
+            Instruction::StoreNumberInRegister {
+                number: 0x81,
+                register: 0x2,
+            },
+            Instruction::RightShiftRegister {
+                register_x: 0x1,
+                register_y: 0x2,
+            },
+        ]);
+        let mut main = Chip8::new();
+        main.load_rom_bytes(&rom).unwrap();
+        let mut compared = Chip8::new();
+        compared.load_rom_bytes(&rom).unwrap();
+        compared.quirks.shift_uses_vy = false;
+        let mut compare = Compare::new(compared);
+
+        for _ in 0..4 {
+            compare.sync_input(&main);
+            main.step_cycle().unwrap();
+            if compare.step(&main) {
+                break;
+            }
+
+            // the frontend ends the wait of the main interpreter only
+            if main.waiting_for_key.is_some() {
+                main.queue_key_event(0x5, true);
+                main.queue_key_event(0x5, false);
+                main.process_key_events();
+            }
+        }
+
+        let divergence = compare
+            .divergence
+            .expect("The shift quirk should make the interpreters differ");
+        assert_eq!(divergence.cycle, 4, "Instructions until the difference");
+        assert_eq!(
+            compare.chip8.registers[0x0], 0x5,
+            "Key passed to the compared interpreter"
+        );
+        assert_eq!(
+            divergence.changes,
+            [
+                Change::Register {
+                    register: 0x1,
+                    before: 0x40,
+                    after: 0x01,
+                },
+                Change::Register {
+                    register: 0xF,
+                    before: 0x1,
+                    after: 0x0,
+                },
+            ]
+        );
+    }
+}
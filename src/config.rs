@@ -0,0 +1,44 @@
+use std::{fs, path::PathBuf};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Directory where configuration files are stored, following the XDG base directory specification
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("chip8stuff"))
+}
+
+/// Load a config file from the config directory.
+/// Returns [None] when the file does not exist yet
+pub fn load<T: DeserializeOwned>(file_name: &str) -> anyhow::Result<Option<T>> {
+    let path = config_path(file_name)?;
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&path)?;
+    Ok(Some(serde_json::from_str(&content)?))
+}
+
+/// Save a config file into the config directory, creating the directory if needed
+pub fn save<T: Serialize>(file_name: &str, value: &T) -> anyhow::Result<()> {
+    let path = config_path(file_name)?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(&path, serde_json::to_string_pretty(value)?)?;
+    log::debug!("Saved {}", path.display());
+
+    Ok(())
+}
+
+fn config_path(file_name: &str) -> anyhow::Result<PathBuf> {
+    config_dir()
+        .map(|dir| dir.join(file_name))
+        .ok_or_else(|| anyhow::anyhow!("Neither XDG_CONFIG_HOME nor HOME are set"))
+}
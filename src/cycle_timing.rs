@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+/// Averages how long cycles of the interpreter thread take over a window of time, so falling
+/// behind the target frequency is only reported when it lasts, not on every short hiccup
+pub struct CycleTiming {
+    target: Duration,
+    /// how much slower than `target` the average may be, in percent
+    threshold_percent: u32,
+    window: Duration,
+    /// sum of the cycles recorded in the current window
+    total: Duration,
+    cycles: u32,
+}
+
+impl CycleTiming {
+    pub fn new(target: Duration, threshold_percent: u32, window: Duration) -> Self {
+        CycleTiming {
+            target,
+            threshold_percent,
+            window,
+            total: Duration::ZERO,
+            cycles: 0,
+        }
+    }
+
+    /// Record how long a cycle took, including the time spent waiting for the next one.
+    /// Returns the average and the number of cycles of the window if it just ended and its
+    /// cycles were slower than allowed
+    pub fn record(&mut self, cycle: Duration) -> Option<(Duration, u32)> {
+        self.total += cycle;
+        self.cycles += 1;
+
+        if self.total < self.window {
+            return None;
+        }
+
+        let average = self.total / self.cycles;
+        let cycles = self.cycles;
+        self.total = Duration::ZERO;
+        self.cycles = 0;
+
+        let allowed = self.target * self.threshold_percent.saturating_add(100) / 100;
+        (average > allowed).then_some((average, cycles))
+    }
+}
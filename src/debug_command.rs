@@ -0,0 +1,76 @@
+use std::str::FromStr;
+
+/// Commands that can be typed into the debugger command line, similar to gdb.
+///
+/// Addresses and values are hexadecimal, with an optional `0x` prefix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugCommand {
+    /// `b ADDR`: pause when the PC reaches the address
+    Break(usize),
+    /// `d ADDR`: remove the breakpoint at the address
+    Delete(usize),
+    /// `c`: continue execution
+    Continue,
+    /// `p`: pause execution
+    Pause,
+    /// `s`: execute a single instruction
+    Step,
+    /// `t`: execute instructions until the next timer tick
+    StepTimerTick,
+    /// `mem ADDR`: show the address in the memory viewer
+    Memory(usize),
+    /// `reg VX=VALUE`: set a register
+    SetRegister { register: usize, value: u8 },
+}
+
+fn parse_hex(value: &str) -> anyhow::Result<u16> {
+    Ok(u16::from_str_radix(
+        value.trim().trim_start_matches("0x"),
+        16,
+    )?)
+}
+
+impl FromStr for DebugCommand {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let command = words
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Empty command"))?;
+        let argument = words.next();
+
+        anyhow::ensure!(words.next().is_none(), "Too many arguments");
+
+        let address = || -> anyhow::Result<usize> {
+            let address = argument.ok_or_else(|| anyhow::anyhow!("{command} needs an address"))?;
+            Ok(parse_hex(address)?.into())
+        };
+
+        Ok(match command {
+            "b" => DebugCommand::Break(address()?),
+            "d" => DebugCommand::Delete(address()?),
+            "c" => DebugCommand::Continue,
+            "p" => DebugCommand::Pause,
+            "s" => DebugCommand::Step,
+            "t" => DebugCommand::StepTimerTick,
+            "mem" => DebugCommand::Memory(address()?),
+            "reg" => {
+                let (register, value) = argument
+                    .and_then(|a| a.split_once('='))
+                    .ok_or_else(|| anyhow::anyhow!("Expected reg VX=VALUE"))?;
+                let register = register
+                    .strip_prefix(['V', 'v'])
+                    .ok_or_else(|| anyhow::anyhow!("Unknown register {register}"))?;
+                let register = usize::from_str_radix(register, 16)?;
+                anyhow::ensure!(register < 16, "Unknown register V{register:X}");
+
+                DebugCommand::SetRegister {
+                    register,
+                    value: u8::try_from(parse_hex(value)?)?,
+                }
+            }
+            _ => anyhow::bail!("Unknown command {command}"),
+        })
+    }
+}
@@ -1,11 +1,63 @@
-use egui::{ClippedPrimitive, Context, TexturesDelta, Ui};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use egui::{ClippedPrimitive, Context, InnerResponse, TexturesDelta, Ui};
 use egui_wgpu::{renderer::ScreenDescriptor, wgpu, Renderer};
 
 use pixels::PixelsContext;
+use serde::{Deserialize, Serialize};
 
 use winit::event_loop::EventLoopWindowTarget;
 
-use crate::chip8::{self, Mode};
+use crate::{
+    chip8::{
+        self,
+        breakpoint::Condition,
+        call_stats::CallStats,
+        instructions::{Instruction, OpcodeInfo},
+        quirks::Quirks,
+        rom_info::RomInfo,
+        snapshot::Change,
+        Mode,
+    },
+    compare::Divergence,
+    config,
+    debug_command::DebugCommand,
+    defaults::Defaults,
+    symbols::Symbols,
+};
+
+/// How long the "ROM reloaded" notice stays visible
+const ROM_RELOADED_NOTICE_DURATION: Duration = Duration::from_secs(2);
+
+const LAYOUT_FILE: &str = "debugger_layout.json";
+
+/// How many of the last executed instructions are shown by default
+pub const DEFAULT_INSTRUCTION_HISTORY_LEN: usize = 20;
+/// Upper limit for the instruction history length, older instructions are dropped
+const MAX_INSTRUCTION_HISTORY_LEN: usize = 200;
+
+/// How many stack depth samples the graph in the registers window shows, one per synced frame
+const STACK_DEPTH_HISTORY_LEN: usize = 600;
+
+/// Bytes per line of the memory viewer
+const MEMORY_VIEWER_ROW_LEN: usize = 16;
+
+/// Keys of the virtual keypad, in the layout of the original hex keypad
+const KEYPAD_ROWS: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// How many instructions the disassembly window shows before and after the PC
+const DISASSEMBLY_INSTRUCTIONS_BEFORE_PC: usize = 8;
+const DISASSEMBLY_INSTRUCTIONS_AFTER_PC: usize = 16;
 
 pub struct EguiFramework {
     // State for egui.
@@ -17,17 +69,451 @@ pub struct EguiFramework {
     textures: TexturesDelta,
 }
 
-pub struct DebugGui {
+/// Which debugger windows are open and where they are, persisted across runs
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct DebuggerLayout {
     pub show_registers: bool,
+    pub show_instruction_history_window: bool,
+    pub show_breakpoints: bool,
+    pub show_quirks: bool,
+    pub show_memory: bool,
+    pub show_disassembly: bool,
+    pub show_step_changes: bool,
+    pub show_preview: bool,
+    pub show_compare: bool,
+    pub show_keypad: bool,
+    pub show_rom_info: bool,
+    /// top left corner of the debugger windows, by window title
+    pub window_positions: HashMap<String, [f32; 2]>,
+}
+
+impl DebuggerLayout {
+    /// Load the layout saved by the last run, falling back to the default layout
+    pub fn load() -> Self {
+        match config::load(LAYOUT_FILE) {
+            Ok(layout) => layout.unwrap_or_default(),
+            Err(e) => {
+                log::warn!("Failed to load debugger layout: {e}");
+                DebuggerLayout::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        config::save(LAYOUT_FILE, self)
+    }
+
+    /// Create a window that opens at its saved position
+    fn window<'open>(&self, title: &str) -> egui::Window<'open> {
+        let window = egui::Window::new(title.to_owned());
+
+        match self.window_positions.get(title) {
+            Some(pos) => window.default_pos(*pos),
+            None => window,
+        }
+    }
+
+    /// Remember where a window is placed
+    fn store_position<R>(&mut self, title: &str, response: Option<InnerResponse<R>>) {
+        if let Some(response) = response {
+            let pos = response.response.rect.min;
+            self.window_positions
+                .insert(title.to_owned(), [pos.x, pos.y]);
+        }
+    }
+}
+
+/// Lines drawn over the display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DisplayOverlay {
+    #[default]
+    None,
+    /// lines between the vram pixels, helps with sprite alignment
+    Grid,
+    /// every other line of the window is darker, like on a CRT
+    Scanlines,
+}
+
+impl Display for DisplayOverlay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DisplayOverlay::None => write!(f, "No overlay"),
+            DisplayOverlay::Grid => write!(f, "Grid"),
+            DisplayOverlay::Scanlines => write!(f, "Scanlines"),
+        }
+    }
+}
+
+/// How the vram pixels are scaled up to the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RenderFilter {
+    /// sharp square pixels
+    #[default]
+    Nearest,
+    /// pixel edges are blended with their neighbours and every other line is slightly darker,
+    /// for a softer image like on a CRT
+    Crt,
+}
+
+impl Display for RenderFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderFilter::Nearest => write!(f, "nearest"),
+            RenderFilter::Crt => write!(f, "crt"),
+        }
+    }
+}
+
+impl FromStr for RenderFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "nearest" => Ok(RenderFilter::Nearest),
+            "crt" => Ok(RenderFilter::Crt),
+            _ => Err(anyhow::anyhow!(
+                "unknown render filter {s}, expected nearest or crt"
+            )),
+        }
+    }
+}
+
+pub enum BreakpointCommand {
+    Add(Condition),
+    Remove(Condition),
+    /// continue until the PC reaches the address
+    RunTo(usize),
+}
+
+/// What a new breakpoint compares
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+enum BreakpointTarget {
+    #[default]
+    Pc,
+    AddressRegister,
+    Register(usize),
+}
+
+impl Display for BreakpointTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BreakpointTarget::Pc => write!(f, "PC"),
+            BreakpointTarget::AddressRegister => write!(f, "I"),
+            BreakpointTarget::Register(register) => write!(f, "V{register:X}"),
+        }
+    }
+}
+
+/// Input of the "new breakpoint" form
+#[derive(Default)]
+pub struct BreakpointForm {
+    target: BreakpointTarget,
+    value: String,
+    error: Option<String>,
+}
+
+impl BreakpointForm {
+    fn condition(&self) -> anyhow::Result<Condition> {
+        let value = u16::from_str_radix(self.value.trim().trim_start_matches("0x"), 16)?;
+
+        Ok(match self.target {
+            BreakpointTarget::Pc => Condition::Pc(value.into()),
+            BreakpointTarget::AddressRegister => Condition::AddressRegister(value),
+            BreakpointTarget::Register(register) => Condition::Register {
+                register,
+                value: u8::try_from(value)?,
+            },
+        })
+    }
+}
+
+/// Which registers changed with the last update
+#[derive(Default)]
+pub struct RegisterChanges {
+    registers: [bool; 16],
+    pc: bool,
+    address_register: bool,
+}
+
+/// State of the interpreter that runs in lockstep, only updated while its window is shown
+pub struct CompareView {
+    pub cycles: u64,
+    pub divergence: Option<Divergence>,
+    /// the main and the compared screen as RGBA pixels without scaling
+    pub screens: [Vec<u8>; 2],
+    textures: [Option<egui::TextureHandle>; 2],
+}
+
+impl Default for CompareView {
+    fn default() -> Self {
+        let screen =
+            vec![0; usize::from(chip8::DISPLAY_WIDTH) * usize::from(chip8::DISPLAY_HEIGHT) * 4];
+
+        CompareView {
+            cycles: 0,
+            divergence: None,
+            screens: [screen.clone(), screen],
+            textures: [None, None],
+        }
+    }
+}
+
+/// What an address is used for, the memory viewer and the disassembly color them differently
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MemoryRegion {
+    Font,
+    /// reserved for the interpreter on the original hardware, unused apart from the font
+    Interpreter,
+    Program,
+    /// written by the ROM while running, e.g. variables or self-modifying code
+    Written,
+}
+
+impl MemoryRegion {
+    fn of(address: usize, written_memory: &[bool]) -> Self {
+        if written_memory.get(address) == Some(&true) {
+            MemoryRegion::Written
+        } else if (chip8::FONT_START..chip8::FONT_END).contains(&address) {
+            MemoryRegion::Font
+        } else if address < chip8::PC_INIT {
+            MemoryRegion::Interpreter
+        } else {
+            MemoryRegion::Program
+        }
+    }
+
+    fn color(self, visuals: &egui::Visuals) -> egui::Color32 {
+        match self {
+            MemoryRegion::Font => egui::Color32::LIGHT_BLUE,
+            MemoryRegion::Interpreter => visuals.weak_text_color(),
+            MemoryRegion::Program => visuals.text_color(),
+            MemoryRegion::Written => visuals.warn_fg_color,
+        }
+    }
+
+    fn description(self) -> String {
+        match self {
+            MemoryRegion::Font => format!(
+                "Font 0x{:03X}-0x{:03X}",
+                chip8::FONT_START,
+                chip8::FONT_END - 1
+            ),
+            MemoryRegion::Interpreter => format!("Interpreter -0x{:03X}", chip8::PC_INIT - 1),
+            MemoryRegion::Program => format!("Program 0x{:03X}-", chip8::PC_INIT),
+            MemoryRegion::Written => "Written by the ROM".to_owned(),
+        }
+    }
+}
+
+/// Explain the colors of the memory regions
+fn memory_legend(ui: &mut Ui) {
+    ui.horizontal(|ui| {
+        for region in [
+            MemoryRegion::Font,
+            MemoryRegion::Interpreter,
+            MemoryRegion::Program,
+            MemoryRegion::Written,
+        ] {
+            let color = region.color(ui.visuals());
+            ui.label(egui::RichText::new(format!("■ {}", region.description())).color(color));
+        }
+    });
+}
+
+/// Part of the screen that is exported as sprite data, selected in the preview
+pub struct SpriteExport {
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
+}
+
+impl Default for SpriteExport {
+    /// The largest sprite `DXYN` can draw
+    fn default() -> Self {
+        SpriteExport {
+            x: 0,
+            y: 0,
+            width: 8,
+            height: 15,
+        }
+    }
+}
+
+impl SpriteExport {
+    /// The sprite bytes as source code, one line of hex bytes per 8 pixel wide column
+    fn source(&self, vram: &[u8]) -> String {
+        let columns = chip8::vram_sprites(vram, self.x, self.y, self.width, self.height);
+
+        let mut source = format!(
+            "; {}x{} pixels at ({}, {}), {} column(s) of 8 pixels\n",
+            self.width,
+            self.height,
+            self.x,
+            self.y,
+            columns.len()
+        );
+        for column in columns {
+            let bytes: Vec<String> = column.iter().map(|b| format!("0x{b:02X}")).collect();
+            source.push_str(&bytes.join(", "));
+            source.push('\n');
+        }
+
+        source
+    }
+}
+
+/// Input of the memory viewer
+#[derive(Default)]
+pub struct MemoryViewer {
+    goto: String,
+    /// the address we jumped to last, it is highlighted
+    goto_address: Option<usize>,
+    /// scroll to the row with this address in the next frame
+    scroll_to: Option<usize>,
+    search: String,
+    /// the parsed search input
+    pattern: Vec<u8>,
+    error: Option<String>,
+}
+
+impl MemoryViewer {
+    fn goto(&mut self, memory_len: usize) {
+        match usize::from_str_radix(self.goto.trim().trim_start_matches("0x"), 16) {
+            Ok(address) if address < memory_len => {
+                self.goto_address = Some(address);
+                self.scroll_to = Some(address);
+                self.error = None;
+            }
+            Ok(address) => self.error = Some(format!("0x{address:X} is outside of memory")),
+            Err(e) => self.error = Some(format!("Invalid address: {e}")),
+        }
+    }
+
+    /// Parse the search input, a sequence of hex bytes like `F0 90 90`
+    fn parse_search(&mut self) {
+        let pattern: Result<Vec<u8>, _> = self
+            .search
+            .split_whitespace()
+            .map(|byte| u8::from_str_radix(byte.trim_start_matches("0x"), 16))
+            .collect();
+
+        match pattern {
+            Ok(pattern) => {
+                self.pattern = pattern;
+                self.error = None;
+            }
+            Err(e) => {
+                self.pattern.clear();
+                self.error = Some(format!("Invalid search: {e}"));
+            }
+        }
+    }
+}
+
+#[allow(clippy::struct_excessive_bools)]
+pub struct DebugGui {
+    pub layout: DebuggerLayout,
     pub chip8_mode: chip8::Mode,
+    /// the register `FX0A` stores the next key in
+    pub waiting_for_key: Option<usize>,
     pub registers: [u8; 16],
     pub set_mode: std::sync::mpsc::Sender<Mode>,
     pub step_sender: std::sync::mpsc::Sender<()>,
-    pub instruction_history: Vec<chip8::instructions::Instruction>,
-    pub show_instruction_history_window: bool,
+    /// execute until the next timer tick
+    pub step_tick_sender: std::sync::mpsc::Sender<()>,
+    /// go back to before the last draw
+    pub previous_frame_sender: std::sync::mpsc::Sender<()>,
+    /// whether single steps also advance the timers
+    pub step_timers: bool,
+    pub step_timers_sender: std::sync::mpsc::Sender<bool>,
+    /// the last executed instructions, at most `instruction_history_len`
+    pub instruction_history: VecDeque<chip8::instructions::Instruction>,
+    pub instruction_history_len: usize,
     pub pc: usize,
     pub address_register: u16,
+    pub register_changes: RegisterChanges,
     pub dump_memory_sender: std::sync::mpsc::Sender<()>,
+    /// when the ROM file was last reloaded because it changed on disk
+    pub rom_reloaded_at: Option<Instant>,
+    pub breakpoints: Vec<Condition>,
+    pub breakpoint_sender: std::sync::mpsc::Sender<BreakpointCommand>,
+    pub breakpoint_form: BreakpointForm,
+    pub save_session_sender: std::sync::mpsc::Sender<()>,
+    pub quirks: Quirks,
+    pub quirks_sender: std::sync::mpsc::Sender<Quirks>,
+    pub memory: Vec<u8>,
+    /// which bytes of memory were written by the ROM
+    pub written_memory: Vec<bool>,
+    pub memory_viewer: MemoryViewer,
+    /// names for addresses, shown in the disassembly
+    pub symbols: Symbols,
+    pub overlay: DisplayOverlay,
+    pub overlay_sender: std::sync::mpsc::Sender<DisplayOverlay>,
+    pub filter: RenderFilter,
+    pub filter_sender: std::sync::mpsc::Sender<RenderFilter>,
+    /// keep showing the current frame while the interpreter continues
+    pub freeze_display: bool,
+    pub freeze_display_sender: std::sync::mpsc::Sender<bool>,
+    /// show the current vram once while the display is frozen
+    pub capture_frame_sender: std::sync::mpsc::Sender<()>,
+    /// input of the command line
+    pub command: String,
+    pub command_error: Option<String>,
+    pub set_register_sender: std::sync::mpsc::Sender<(usize, u8)>,
+    /// ask whether the user really wants to quit
+    pub show_quit_dialog: bool,
+    pub quit_confirmed: bool,
+    /// what the last single step changed, cleared while running continuously
+    pub step_changes: Option<Vec<Change>>,
+    /// the screen as RGBA pixels without scaling, only updated while the preview is shown
+    pub preview: Vec<u8>,
+    pub preview_texture: Option<egui::TextureHandle>,
+    /// the screen, only updated while the preview is shown
+    pub vram: Vec<u8>,
+    pub sprite_export: SpriteExport,
+    /// the interpreter that runs in lockstep, with `--compare`
+    pub compare: Option<CompareView>,
+    /// keys that are down, from the keyboard or the keypad window, bit n is key n
+    pub keys: u16,
+    /// keys held down with the mouse in the keypad window, merged with the keyboard by the frontend
+    pub virtual_keys: u16,
+    /// None if the state was restored from a session
+    pub rom_info: Option<RomInfo>,
+    pub call_stats: CallStats,
+    /// the stack depth at the last synced frames, oldest first, at most [`STACK_DEPTH_HISTORY_LEN`]
+    pub stack_depth_history: VecDeque<usize>,
+    /// show the performance HUD over the display, toggled with F3
+    pub show_hud: bool,
+    pub performance: Performance,
+}
+
+/// What the interpreter thread achieved during the last second, shown by the HUD
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Performance {
+    /// timer ticks per second
+    pub fps: f64,
+    /// executed instructions per second
+    pub ips: f64,
+    /// executed instructions per timer tick
+    pub ipf: f64,
+}
+
+impl Performance {
+    /// From what was counted during `seconds`
+    pub fn new(instructions: f64, frames: f64, seconds: f64) -> Self {
+        Performance {
+            fps: frames / seconds,
+            ips: instructions / seconds,
+            ipf: if frames > 0.0 {
+                instructions / frames
+            } else {
+                0.0
+            },
+        }
+    }
 }
 
 impl EguiFramework {
@@ -63,8 +549,9 @@ impl EguiFramework {
     }
 
     /// Handle input events from the window manager.
-    pub(crate) fn handle_event(&mut self, event: &winit::event::WindowEvent) {
-        let _ = self.egui_state.on_event(&self.egui_ctx, event);
+    /// Returns whether egui has to be redrawn because of the event
+    pub(crate) fn handle_event(&mut self, event: &winit::event::WindowEvent) -> bool {
+        self.egui_state.on_event(&self.egui_ctx, event).repaint
     }
 
     /// Resize egui.
@@ -75,12 +562,18 @@ impl EguiFramework {
     }
 
     /// Update scaling factor.
+    #[allow(clippy::cast_possible_truncation)]
     pub(crate) fn scale_factor(&mut self, scale_factor: f64) {
         self.screen_descriptor.pixels_per_point = scale_factor as f32;
     }
 
     /// Prepare egui.
-    pub(crate) fn prepare(&mut self, window: &winit::window::Window, model: &mut DebugGui) {
+    /// Returns how long egui can wait before it has to be drawn again, e.g. for animations
+    pub(crate) fn prepare(
+        &mut self,
+        window: &winit::window::Window,
+        model: &mut DebugGui,
+    ) -> Duration {
         // Run the egui frame and create all paint jobs to prepare for rendering.
         let raw_input = self.egui_state.take_egui_input(window);
         let output = self.egui_ctx.run(raw_input, |egui_ctx| {
@@ -92,6 +585,8 @@ impl EguiFramework {
         self.egui_state
             .handle_platform_output(window, &self.egui_ctx, output.platform_output);
         self.paint_jobs = self.egui_ctx.tessellate(output.shapes);
+
+        output.repaint_after
     }
 
     /// Render egui.
@@ -142,59 +637,384 @@ impl EguiFramework {
 }
 
 impl DebugGui {
+    /// Update the register values and remember which ones changed. The highlighting is kept
+    /// until the registers change again, so it still shows what a single step modified
+    pub fn update_registers(&mut self, registers: [u8; 16], pc: usize, address_register: u16) {
+        if registers == self.registers && pc == self.pc && address_register == self.address_register
+        {
+            return;
+        }
+
+        self.register_changes = RegisterChanges {
+            registers: std::array::from_fn(|i| registers[i] != self.registers[i]),
+            pc: pc != self.pc,
+            address_register: address_register != self.address_register,
+        };
+
+        self.registers = registers;
+        self.pc = pc;
+        self.address_register = address_register;
+    }
+
+    /// Add an executed instruction to the history, dropping the oldest ones
+    pub fn record_instruction(&mut self, instruction: chip8::instructions::Instruction) {
+        self.instruction_history.push_back(instruction);
+        drop_oldest(&mut self.instruction_history, self.instruction_history_len);
+    }
+
+    /// Update the call stats and sample the stack depth for the graph, dropping the oldest samples
+    pub fn record_call_stats(&mut self, call_stats: CallStats) {
+        self.call_stats = call_stats;
+        self.stack_depth_history.push_back(call_stats.depth);
+        drop_oldest(&mut self.stack_depth_history, STACK_DEPTH_HISTORY_LEN);
+    }
+
     /// Create the UI using egui.
     fn ui(&mut self, ctx: &Context) {
         egui::TopBottomPanel::top("menubar_container").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
-                self.play_pause_step(ctx, ui);
+                self.play_pause_step(ui);
 
                 if ui.button("Registers").clicked() {
-                    self.show_registers = !self.show_registers;
+                    self.layout.show_registers = !self.layout.show_registers;
                 }
 
                 if ui.button("Instructions").clicked() {
-                    self.show_instruction_history_window = !self.show_instruction_history_window;
+                    self.layout.show_instruction_history_window =
+                        !self.layout.show_instruction_history_window;
+                }
+
+                if ui.button("Breakpoints").clicked() {
+                    self.layout.show_breakpoints = !self.layout.show_breakpoints;
+                }
+
+                if ui.button("Disassembly").clicked() {
+                    self.layout.show_disassembly = !self.layout.show_disassembly;
+                }
+
+                if ui.button("Last step").clicked() {
+                    self.layout.show_step_changes = !self.layout.show_step_changes;
+                }
+
+                if ui.button("Preview").clicked() {
+                    self.layout.show_preview = !self.layout.show_preview;
+                }
+
+                if self.compare.is_some() && ui.button("Compare").clicked() {
+                    self.layout.show_compare = !self.layout.show_compare;
+                }
+
+                if ui.button("Keypad").clicked() {
+                    self.layout.show_keypad = !self.layout.show_keypad;
+                }
+
+                if ui.button("Memory").clicked() {
+                    self.layout.show_memory = !self.layout.show_memory;
+                }
+
+                if ui.button("ROM").clicked() {
+                    self.layout.show_rom_info = !self.layout.show_rom_info;
+                }
+
+                if ui.button("Quirks").clicked() {
+                    self.layout.show_quirks = !self.layout.show_quirks;
+                }
+
+                let overlay = self.overlay;
+                egui::ComboBox::from_id_source("display_overlay")
+                    .selected_text(self.overlay.to_string())
+                    .show_ui(ui, |ui| {
+                        for overlay in [
+                            DisplayOverlay::None,
+                            DisplayOverlay::Grid,
+                            DisplayOverlay::Scanlines,
+                        ] {
+                            ui.selectable_value(&mut self.overlay, overlay, overlay.to_string());
+                        }
+                    });
+                if self.overlay != overlay {
+                    self.overlay_sender.send(self.overlay).unwrap();
+                }
+
+                let filter = self.filter;
+                egui::ComboBox::from_id_source("render_filter")
+                    .selected_text(format!("Filter: {}", self.filter))
+                    .show_ui(ui, |ui| {
+                        for filter in [RenderFilter::Nearest, RenderFilter::Crt] {
+                            ui.selectable_value(&mut self.filter, filter, filter.to_string());
+                        }
+                    });
+                if self.filter != filter {
+                    self.filter_sender.send(self.filter).unwrap();
+                }
+
+                if ui
+                    .checkbox(&mut self.freeze_display, "Freeze display")
+                    .on_hover_text("Keep showing the current frame while the ROM continues to run")
+                    .changed()
+                {
+                    self.freeze_display_sender
+                        .send(self.freeze_display)
+                        .unwrap();
+                }
+                if self.freeze_display && ui.button("Capture frame").clicked() {
+                    self.capture_frame_sender.send(()).unwrap();
                 }
 
                 if ui.button("Memory dump").clicked() {
                     self.dump_memory_sender.send(()).unwrap();
                 }
+
+                if ui.button("Save session").clicked() {
+                    self.save_session_sender.send(()).unwrap();
+                }
+
+                if ui.button("Reset layout").clicked() {
+                    self.layout = DebuggerLayout::default();
+                    ctx.memory_mut(egui::Memory::reset_areas);
+                }
+
+                self.command_line(ui);
+
+                if self
+                    .rom_reloaded_at
+                    .is_some_and(|t| t.elapsed() < ROM_RELOADED_NOTICE_DURATION)
+                {
+                    ui.label("ROM reloaded");
+                    // hide the notice even if nothing else changes
+                    ui.ctx().request_repaint_after(ROM_RELOADED_NOTICE_DURATION);
+                }
             });
         });
 
         self.register_window(ctx);
 
         self.instruction_history_window(ctx);
+
+        self.breakpoint_window(ctx);
+
+        self.quirks_window(ctx);
+
+        self.memory_window(ctx);
+
+        self.disassembly_window(ctx);
+
+        self.step_changes_window(ctx);
+
+        self.preview_window(ctx);
+
+        self.compare_window(ctx);
+
+        self.keypad_window(ctx);
+
+        self.rom_info_window(ctx);
+
+        self.status_overlay(ctx);
+        if self.show_hud {
+            self.hud(ctx);
+        }
+
+        self.quit_dialog(ctx);
     }
 
-    fn play_pause_step(&mut self, ctx: &Context, ui: &mut Ui) {
+    fn quit_dialog(&mut self, ctx: &Context) {
+        if !self.show_quit_dialog {
+            return;
+        }
+
+        egui::Window::new("Quit?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label("The current state of the interpreter will be lost.");
+                ui.horizontal(|ui| {
+                    if ui.button("Quit").clicked() {
+                        self.quit_confirmed = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.show_quit_dialog = false;
+                    }
+                });
+            });
+    }
+
+    fn command_line(&mut self, ui: &mut Ui) {
+        let input = ui.add(
+            egui::TextEdit::singleline(&mut self.command)
+                .hint_text("command")
+                .desired_width(100.0),
+        );
+        let input = match &self.command_error {
+            Some(error) => input.on_hover_text(error),
+            None => input.on_hover_text("b/d ADDR, c, p, s, t, mem ADDR, reg VX=VALUE"),
+        };
+
+        if input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            match self.command.parse() {
+                Ok(command) => {
+                    self.run_command(command);
+                    self.command.clear();
+                    self.command_error = None;
+                }
+                Err(e) => self.command_error = Some(e.to_string()),
+            }
+
+            // keep typing commands
+            input.request_focus();
+        }
+
+        if let Some(error) = &self.command_error {
+            ui.label(error);
+        }
+    }
+
+    fn run_command(&mut self, command: DebugCommand) {
+        match command {
+            DebugCommand::Break(address) => self
+                .breakpoint_sender
+                .send(BreakpointCommand::Add(Condition::Pc(address)))
+                .unwrap(),
+            DebugCommand::Delete(address) => self
+                .breakpoint_sender
+                .send(BreakpointCommand::Remove(Condition::Pc(address)))
+                .unwrap(),
+            // like the buttons, don't interfere with an instruction that waits for a key
+            DebugCommand::Continue if self.chip8_mode == Mode::Paused => {
+                self.set_mode.send(Mode::Running).unwrap();
+            }
+            DebugCommand::Pause if self.chip8_mode == Mode::Running => {
+                self.set_mode.send(Mode::Paused).unwrap();
+            }
+            DebugCommand::Step if self.chip8_mode == Mode::Paused => {
+                self.step_sender.send(()).unwrap();
+            }
+            DebugCommand::StepTimerTick if self.chip8_mode == Mode::Paused => {
+                self.step_tick_sender.send(()).unwrap();
+            }
+            DebugCommand::Continue
+            | DebugCommand::Pause
+            | DebugCommand::Step
+            | DebugCommand::StepTimerTick => {}
+            DebugCommand::Memory(address) => {
+                self.layout.show_memory = true;
+                self.memory_viewer.goto = format!("{address:X}");
+                self.memory_viewer.goto(self.memory.len());
+            }
+            DebugCommand::SetRegister { register, value } => {
+                self.set_register_sender.send((register, value)).unwrap();
+            }
+        }
+    }
+
+    /// Explain over the display why nothing is moving
+    fn status_overlay(&self, ctx: &Context) {
+        let status = match (self.chip8_mode, self.waiting_for_key) {
+            (Mode::Running, None) => return,
+            (Mode::Running, Some(register)) => format!("WAITING FOR KEY (V{register:X})"),
+            (Mode::Paused, None) => "PAUSED".to_owned(),
+            (Mode::Paused, Some(register)) => format!("PAUSED, WAITING FOR KEY (V{register:X})"),
+            (Mode::Halted, _) => "HALTED".to_owned(),
+        };
+
+        egui::Area::new("status_overlay")
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.heading(status);
+                });
+            });
+    }
+
+    /// The achieved speed in the corner of the display, updated once per second
+    fn hud(&self, ctx: &Context) {
+        let performance = &self.performance;
+        egui::Area::new("performance_hud")
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.monospace(format!("FPS {:>6.1}", performance.fps));
+                    ui.monospace(format!("IPS {:>6.0}", performance.ips));
+                    ui.monospace(format!("IPF {:>6.1}", performance.ipf));
+                });
+            });
+    }
+
+    fn play_pause_step(&mut self, ui: &mut Ui) {
         let (label, new_mode) = match self.chip8_mode {
             Mode::Running => ("Pause", Mode::Paused),
-            Mode::WaitForKey { register } => ("GETKEY", Mode::WaitForKey { register }),
             Mode::Paused => ("Play", Mode::Running),
+            // only reloading the ROM gets out of here
+            Mode::Halted => ("HALTED", Mode::Halted),
         };
 
         if ui.button(label).clicked() {
             self.set_mode.send(new_mode).unwrap();
         }
 
-        if self.chip8_mode == Mode::Paused && ui.button("Step").clicked() {
-            self.step_sender.send(()).unwrap();
+        if self.chip8_mode == Mode::Paused {
+            if ui
+                .add_enabled(self.waiting_for_key.is_none(), egui::Button::new("Step"))
+                .on_disabled_hover_text("FX0A is waiting for a key to be released")
+                .clicked()
+            {
+                self.step_sender.send(()).unwrap();
+            }
+
+            if ui
+                .button("Step Tick")
+                .on_hover_text(
+                    "Execute instructions until the next 60 Hz timer tick, the delay timer decreases by one",
+                )
+                .clicked()
+            {
+                self.step_tick_sender.send(()).unwrap();
+            }
+
+            if ui
+                .button("Prev Frame")
+                .on_hover_text(
+                    "Go back to right before the last instruction that changed the screen",
+                )
+                .clicked()
+            {
+                self.previous_frame_sender.send(()).unwrap();
+            }
+
+            if ui
+                .checkbox(&mut self.step_timers, "Step timers")
+                .on_hover_text(
+                    "Advance the timers while stepping, as if the instructions were executed at full speed",
+                )
+                .changed()
+            {
+                self.step_timers_sender.send(self.step_timers).unwrap();
+            }
         }
     }
 
     fn register_window(&mut self, ctx: &Context) {
-        egui::Window::new("Registers")
-            .open(&mut self.show_registers)
+        let response = self
+            .layout
+            .window("Registers")
+            .open(&mut self.layout.show_registers)
             .show(ctx, |ui| {
                 ui.group(|ui| {
                     egui::Grid::new("register_grid").show(ui, |ui| {
+                        let changes = &self.register_changes;
+
                         ui.label("PC:");
-                        ui.label(format!("{:X}", self.pc));
+                        register_value(ui, format!("{:X}", self.pc), changes.pc);
                         ui.end_row();
 
                         ui.label("I:");
-                        ui.label(format!("{:X}", self.address_register));
+                        register_value(
+                            ui,
+                            format!("{:X}", self.address_register),
+                            changes.address_register,
+                        );
                         ui.end_row();
                     });
                 });
@@ -202,24 +1022,668 @@ impl DebugGui {
                 ui.group(|ui| {
                     egui::Grid::new("register_grid2").show(ui, |ui| {
                         for i in 0..16 {
-                            ui.label(format!("{i:X}:"));
-                            ui.label(format!("{:X}", self.registers[i]));
+                            let label = ui.label(format!("{i:X}:"));
+                            if i == 0xF {
+                                label.on_hover_text(
+                                    "VF holds the flag of the last arithmetic instruction or draw:\n\
+                                     8XY4: 1 on a carry\n\
+                                     8XY5/8XY7: 1 when there is NO borrow, also for equal operands\n\
+                                     8XY6/8XYE: the shifted out bit\n\
+                                     DXYN: 1 on a collision",
+                                );
+                            }
+                            register_value(
+                                ui,
+                                format!("{:X}", self.registers[i]),
+                                self.register_changes.registers[i],
+                            );
                             ui.end_row();
                         }
                     });
                 });
+
+                ui.group(|ui| {
+                    let stats = &self.call_stats;
+                    ui.label(format!("Stack depth: {}", stats.depth))
+                        .on_hover_text(
+                            "Return addresses on the stack. A ROM that keeps calling subroutines \
+                             without returning leaks stack frames and grows it",
+                        );
+                    ui.label(format!("Calls: {}, returns: {}", stats.calls, stats.returns));
+                    ui.label(format!("Max depth: {}", stats.max_depth));
+
+                    egui::CollapsingHeader::new("Depth over time")
+                        .id_source("stack_depth_graph")
+                        .show(ui, |ui| {
+                            stack_depth_graph(ui, &self.stack_depth_history);
+                        })
+                        .header_response
+                        .on_hover_text(
+                            "The stack depth at the last frames. A line that keeps rising hints at runaway recursion",
+                        );
+                });
             });
+        self.layout.store_position("Registers", response);
     }
 
     fn instruction_history_window(&mut self, ctx: &Context) {
-        egui::Window::new("Instructions")
-            .open(&mut self.show_instruction_history_window)
+        let response = self
+            .layout
+            .window("Instructions")
+            .open(&mut self.layout.show_instruction_history_window)
             .scroll2([false, true])
             .show(ctx, |ui| {
-                for instruction in self.instruction_history.iter().rev().take(20).rev() {
+                ui.horizontal(|ui| {
+                    ui.label("Show last");
+                    ui.add(
+                        egui::DragValue::new(&mut self.instruction_history_len)
+                            .clamp_range(1..=MAX_INSTRUCTION_HISTORY_LEN),
+                    );
+                });
+                drop_oldest(&mut self.instruction_history, self.instruction_history_len);
+
+                for instruction in &self.instruction_history {
                     ui.label(format!("{instruction:?}"));
                     ui.end_row();
                 }
             });
+        self.layout.store_position("Instructions", response);
+    }
+
+    fn breakpoint_window(&mut self, ctx: &Context) {
+        let response = self
+            .layout
+            .window("Breakpoints")
+            .open(&mut self.layout.show_breakpoints)
+            .show(ctx, |ui| {
+                for condition in &self.breakpoints {
+                    ui.horizontal(|ui| {
+                        ui.label(condition.to_string());
+                        if ui.button("Remove").clicked() {
+                            self.breakpoint_sender
+                                .send(BreakpointCommand::Remove(*condition))
+                                .unwrap();
+                        }
+                    });
+                }
+
+                ui.separator();
+
+                let form = &mut self.breakpoint_form;
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("breakpoint_target")
+                        .selected_text(form.target.to_string())
+                        .show_ui(ui, |ui| {
+                            let targets = [BreakpointTarget::Pc, BreakpointTarget::AddressRegister]
+                                .into_iter()
+                                .chain((0..16).map(BreakpointTarget::Register));
+
+                            for target in targets {
+                                ui.selectable_value(&mut form.target, target, target.to_string());
+                            }
+                        });
+
+                    ui.label("== 0x");
+                    ui.text_edit_singleline(&mut form.value);
+
+                    if ui.button("Add").clicked() {
+                        match form.condition() {
+                            Ok(condition) => {
+                                self.breakpoint_sender
+                                    .send(BreakpointCommand::Add(condition))
+                                    .unwrap();
+                                form.error = None;
+                            }
+                            Err(e) => form.error = Some(format!("Invalid value: {e}")),
+                        }
+                    }
+                });
+
+                if let Some(error) = &form.error {
+                    ui.label(error);
+                }
+            });
+        self.layout.store_position("Breakpoints", response);
+    }
+
+    /// Which ROM is loaded, to confirm it is the expected one and to mention it in bug reports
+    fn rom_info_window(&mut self, ctx: &Context) {
+        let response = self
+            .layout
+            .window("ROM info")
+            .open(&mut self.layout.show_rom_info)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let Some(rom_info) = &self.rom_info else {
+                    ui.label("The ROM is part of the restored session");
+                    return;
+                };
+
+                egui::Grid::new("rom_info_grid").show(ui, |ui| {
+                    ui.label("Name:");
+                    ui.label(rom_info.name.as_deref().unwrap_or("-"));
+                    ui.end_row();
+
+                    ui.label("Size:");
+                    ui.label(format!("{} bytes", rom_info.len));
+                    ui.end_row();
+
+                    ui.label("CRC32:");
+                    ui.monospace(format!("{:08X}", rom_info.crc32));
+                    ui.end_row();
+                });
+
+                if ui.button("Copy").clicked() {
+                    ui.output_mut(|o| o.copied_text = rom_info.to_string());
+                }
+            });
+        self.layout.store_position("ROM info", response);
+    }
+
+    /// Hex keys that are down while they are held with the mouse, for devices without the
+    /// right keys. Keys that are down from either source are highlighted
+    fn keypad_window(&mut self, ctx: &Context) {
+        // release everything when the window is closed while a key is held
+        self.virtual_keys = 0;
+
+        if !self.layout.show_keypad {
+            return;
+        }
+
+        let response = self
+            .layout
+            .window("Keypad")
+            .open(&mut self.layout.show_keypad)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("keypad_grid").show(ui, |ui| {
+                    for row in KEYPAD_ROWS {
+                        for key in row {
+                            let mut button = egui::Button::new(
+                                egui::RichText::new(format!("{key:X}")).monospace(),
+                            )
+                            .min_size(egui::vec2(32.0, 32.0))
+                            .sense(egui::Sense::drag());
+                            if self.keys & (1 << key) != 0 {
+                                button = button.fill(ui.visuals().selection.bg_fill);
+                            }
+
+                            if ui.add(button).is_pointer_button_down_on() {
+                                self.virtual_keys |= 1 << key;
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        self.layout.store_position("Keypad", response);
+    }
+
+    fn preview_window(&mut self, ctx: &Context) {
+        if !self.layout.show_preview {
+            return;
+        }
+
+        let texture_id = screen_texture(ctx, &mut self.preview_texture, "preview", &self.preview);
+
+        let response = self
+            .layout
+            .window("Preview")
+            .open(&mut self.layout.show_preview)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let export = &mut self.sprite_export;
+                let image = ui.image(
+                    texture_id,
+                    [
+                        f32::from(chip8::DISPLAY_WIDTH),
+                        f32::from(chip8::DISPLAY_HEIGHT),
+                    ],
+                );
+
+                // one point per pixel, so the selection can be drawn in screen pixels
+                let selection = egui::Rect::from_min_size(
+                    image.rect.min + egui::vec2(f32::from(export.x), f32::from(export.y)),
+                    egui::vec2(f32::from(export.width), f32::from(export.height)),
+                );
+                ui.painter().rect_stroke(
+                    selection,
+                    0.0,
+                    egui::Stroke::new(1.0, egui::Color32::LIGHT_RED),
+                );
+
+                ui.horizontal(|ui| {
+                    ui.label("x");
+                    ui.add(
+                        egui::DragValue::new(&mut export.x)
+                            .clamp_range(0..=chip8::DISPLAY_WIDTH - 1),
+                    );
+                    ui.label("y");
+                    ui.add(
+                        egui::DragValue::new(&mut export.y)
+                            .clamp_range(0..=chip8::DISPLAY_HEIGHT - 1),
+                    );
+                    ui.label("w");
+                    ui.add(
+                        egui::DragValue::new(&mut export.width)
+                            .clamp_range(1..=chip8::DISPLAY_WIDTH - export.x),
+                    );
+                    ui.label("h");
+                    ui.add(
+                        egui::DragValue::new(&mut export.height)
+                            .clamp_range(1..=chip8::DISPLAY_HEIGHT - export.y),
+                    );
+                });
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Copy sprite")
+                        .on_hover_text("Copy the selected pixels as sprite bytes")
+                        .clicked()
+                    {
+                        let source = export.source(&self.vram);
+                        ui.output_mut(|o| o.copied_text = source);
+                    }
+
+                    if ui.button("Save sprite").clicked() {
+                        let p = format!("sprite_{}.txt", crate::file_timestamp());
+                        match std::fs::write(&p, export.source(&self.vram)) {
+                            Ok(()) => log::info!("Saved sprite to {p}"),
+                            Err(e) => log::error!("Failed to save sprite: {e}"),
+                        }
+                    }
+                });
+            });
+        self.layout.store_position("Preview", response);
+    }
+
+    fn compare_window(&mut self, ctx: &Context) {
+        let Some(view) = &mut self.compare else {
+            return;
+        };
+        if !self.layout.show_compare {
+            return;
+        }
+
+        let texture_ids = [
+            screen_texture(ctx, &mut view.textures[0], "compare_main", &view.screens[0]),
+            screen_texture(
+                ctx,
+                &mut view.textures[1],
+                "compare_other",
+                &view.screens[1],
+            ),
+        ];
+
+        let response = self
+            .layout
+            .window("Compare")
+            .open(&mut self.layout.show_compare)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for (texture_id, name) in texture_ids.into_iter().zip(["Main", "Compared"]) {
+                        ui.vertical(|ui| {
+                            ui.label(name);
+                            ui.image(
+                                texture_id,
+                                [
+                                    f32::from(chip8::DISPLAY_WIDTH) * 2.0,
+                                    f32::from(chip8::DISPLAY_HEIGHT) * 2.0,
+                                ],
+                            );
+                        });
+                    }
+                });
+
+                let Some(divergence) = &view.divergence else {
+                    ui.label(format!("Identical after {} instructions", view.cycles));
+                    return;
+                };
+
+                ui.label(format!(
+                    "Different after {} instructions (main -> compared):",
+                    divergence.cycle
+                ));
+                if let Some(error) = &divergence.error {
+                    ui.label(format!("The compared interpreter failed: {error}"));
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        for change in &divergence.changes {
+                            ui.label(change.to_string());
+                        }
+                    });
+            });
+        self.layout.store_position("Compare", response);
+    }
+
+    fn step_changes_window(&mut self, ctx: &Context) {
+        let response = self
+            .layout
+            .window("Last step")
+            .open(&mut self.layout.show_step_changes)
+            .show(ctx, |ui| match &self.step_changes {
+                None => {
+                    ui.label("Step while paused to see what an instruction changes");
+                }
+                Some(changes) if changes.is_empty() => {
+                    ui.label("Nothing changed");
+                }
+                Some(changes) => {
+                    for change in changes {
+                        ui.label(change.to_string());
+                    }
+                }
+            });
+        self.layout.store_position("Last step", response);
+    }
+
+    fn quirks_window(&mut self, ctx: &Context) {
+        let paused = self.chip8_mode == Mode::Paused;
+        let response = self
+            .layout
+            .window("Quirks")
+            .open(&mut self.layout.show_quirks)
+            .show(ctx, |ui| {
+                ui.label(format!("Draw mode: {}", self.quirks.draw_mode));
+                ui.label(format!("Collision: {}", self.quirks.collision));
+                ui.label(format!("PC overflow: {}", self.quirks.pc_overflow));
+                ui.label(match (self.quirks.wrap_x, self.quirks.wrap_y) {
+                    (true, true) => "Sprites wrap around the screen edges",
+                    (true, false) => "Sprites wrap around the left and right edges, but are clipped at the bottom",
+                    (false, true) => "Sprites wrap around the top and bottom edges, but are clipped at the right",
+                    (false, false) => "Sprites are clipped at the screen edges",
+                });
+
+                let shift_changed = quirk_checkbox(
+                    ui,
+                    paused,
+                    &mut self.quirks.shift_uses_vy,
+                    "Shift VY into VX",
+                    "8XY6/8XYE shift VY like the original interpreter, otherwise VX is shifted in place like on the SCHIP",
+                );
+                let increment_i_changed = quirk_checkbox(
+                    ui,
+                    paused,
+                    &mut self.quirks.increment_i_on_store,
+                    "Increment I on store/load",
+                    "FX55/FX65 leave I behind the last register like the original interpreter, otherwise I is unchanged like on the SCHIP",
+                );
+
+                let jump_changed = quirk_checkbox(
+                    ui,
+                    paused,
+                    &mut self.quirks.jump_v0_uses_vx,
+                    "Jump to XNN + VX",
+                    "BNNN is read as BXNN and adds VX like on the SCHIP, otherwise V0 is added like on the original interpreter",
+                );
+
+                // only delays instructions, so unlike the other quirks it can be toggled while running
+                let display_wait_changed = ui
+                    .checkbox(&mut self.quirks.display_wait, "Wait for display")
+                    .on_hover_text("DXYN waits for the next frame like the original interpreter, so ROMs draw at most one sprite per frame. Reduces flicker, but slows down ROMs that draw a lot")
+                    .changed();
+
+                if shift_changed || increment_i_changed || jump_changed || display_wait_changed {
+                    self.quirks_sender.send(self.quirks).unwrap();
+                }
+
+                if ui
+                    .button("Save as default")
+                    .on_hover_text("Use these quirks, the display overlay and the filter for every ROM, unless the command line says otherwise")
+                    .clicked()
+                {
+                    let defaults = Defaults {
+                        quirks: Some(self.quirks),
+                        overlay: self.overlay,
+                        filter: Some(self.filter),
+                        ..Defaults::load()
+                    };
+                    match defaults.save() {
+                        Ok(()) => log::info!("Saved the quirks, display overlay and filter as defaults"),
+                        Err(e) => log::error!("Failed to save defaults: {e}"),
+                    }
+                }
+            });
+        self.layout.store_position("Quirks", response);
+    }
+
+    fn memory_window(&mut self, ctx: &Context) {
+        let viewer = &mut self.memory_viewer;
+        let memory = &self.memory;
+        let written_memory = &self.written_memory;
+        let response = self
+            .layout
+            .window("Memory")
+            .open(&mut self.layout.show_memory)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Go to 0x");
+                    let input =
+                        ui.add(egui::TextEdit::singleline(&mut viewer.goto).desired_width(40.0));
+                    let submitted =
+                        input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+                    if ui.button("Go").clicked() || submitted {
+                        viewer.goto(memory.len());
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Search");
+                    if ui
+                        .text_edit_singleline(&mut viewer.search)
+                        .on_hover_text("Hex bytes, e.g. F0 90 90")
+                        .changed()
+                    {
+                        viewer.parse_search();
+                    }
+                });
+
+                // search the current memory every frame, so the matches follow changes made by the ROM
+                let mut highlighted = vec![false; memory.len()];
+                if !viewer.pattern.is_empty() {
+                    let pattern_len = viewer.pattern.len();
+                    let matches: Vec<usize> = memory
+                        .windows(pattern_len)
+                        .enumerate()
+                        .filter(|(_, bytes)| *bytes == viewer.pattern.as_slice())
+                        .map(|(address, _)| address)
+                        .collect();
+
+                    for address in &matches {
+                        highlighted[*address..*address + pattern_len].fill(true);
+                    }
+
+                    ui.label(format!("{} matches", matches.len()));
+                }
+
+                if let Some(error) = &viewer.error {
+                    ui.label(error);
+                }
+
+                memory_legend(ui);
+                ui.separator();
+
+                let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                let mut scroll_area = egui::ScrollArea::vertical().max_height(300.0);
+                if let Some(address) = viewer.scroll_to.take() {
+                    // memory is small enough that the row always fits
+                    let row = u16::try_from(address / MEMORY_VIEWER_ROW_LEN).unwrap_or(u16::MAX);
+                    scroll_area = scroll_area.vertical_scroll_offset(
+                        f32::from(row) * (row_height + ui.spacing().item_spacing.y),
+                    );
+                }
+
+                let rows = memory.len().div_ceil(MEMORY_VIEWER_ROW_LEN);
+                scroll_area.show_rows(ui, row_height, rows, |ui, rows| {
+                    for row in rows {
+                        let start = row * MEMORY_VIEWER_ROW_LEN;
+                        let end = memory.len().min(start + MEMORY_VIEWER_ROW_LEN);
+
+                        ui.horizontal(|ui| {
+                            ui.monospace(format!("{start:03X}:"));
+
+                            for address in start..end {
+                                let region = MemoryRegion::of(address, written_memory);
+                                let mut text =
+                                    egui::RichText::new(format!("{:02X}", memory[address]))
+                                        .monospace()
+                                        .color(region.color(ui.visuals()));
+                                if highlighted[address] {
+                                    text = text.background_color(ui.visuals().selection.bg_fill);
+                                }
+                                if viewer.goto_address == Some(address) {
+                                    text = text.underline();
+                                }
+                                ui.label(text);
+                            }
+                        });
+                    }
+                });
+            });
+        self.layout.store_position("Memory", response);
     }
+
+    fn disassembly_window(&mut self, ctx: &Context) {
+        let response = self
+            .layout
+            .window("Disassembly")
+            .open(&mut self.layout.show_disassembly)
+            .show(ctx, |ui| {
+                memory_legend(ui);
+                ui.separator();
+
+                // instructions are 2 bytes long, so the listing starts at an address that lines up with the PC
+                let start = self
+                    .pc
+                    .saturating_sub(DISASSEMBLY_INSTRUCTIONS_BEFORE_PC * 2);
+                let end = self
+                    .memory
+                    .len()
+                    .min(self.pc + DISASSEMBLY_INSTRUCTIONS_AFTER_PC * 2);
+
+                for address in (start..end).step_by(2) {
+                    if let Some(name) = self.symbols.get(address) {
+                        ui.monospace(format!("{name}:"));
+                    }
+
+                    let Some(&[hi, lo]) = self.memory.get(address..address + 2) else {
+                        break;
+                    };
+                    let opcode = u16::from(hi) << 8 | u16::from(lo);
+                    let instruction = Instruction::try_from_lenient(opcode);
+
+                    // an instruction counts as written if the ROM changed either byte
+                    let region = if self.written_memory.get(address + 1) == Some(&true) {
+                        MemoryRegion::Written
+                    } else {
+                        MemoryRegion::of(address, &self.written_memory)
+                    };
+                    let text =
+                        egui::RichText::new(format!("{address:03X}  {opcode:04X}  {instruction}"))
+                            .monospace()
+                            .color(region.color(ui.visuals()));
+                    let text = if address == self.pc {
+                        text.background_color(ui.visuals().selection.bg_fill)
+                    } else {
+                        text
+                    };
+                    let response = ui.add(egui::Label::new(text).sense(egui::Sense::click()));
+                    if response.clicked() {
+                        self.breakpoint_sender
+                            .send(BreakpointCommand::RunTo(address))
+                            .unwrap();
+                    }
+
+                    let hover_text = match OpcodeInfo::find(opcode) {
+                        Some(info) => format!("{}: {}", info.pattern, info.description),
+                        None => "Data".to_owned(),
+                    };
+                    response.on_hover_text(format!("{hover_text}\nClick to run to this address"));
+                }
+            });
+        self.layout.store_position("Disassembly", response);
+    }
+}
+
+/// Upload a screen rendered without scaling into a texture, creating it on first use
+fn screen_texture(
+    ctx: &Context,
+    texture: &mut Option<egui::TextureHandle>,
+    name: &str,
+    rgba: &[u8],
+) -> egui::TextureId {
+    let image = egui::ColorImage::from_rgba_unmultiplied(
+        [
+            usize::from(chip8::DISPLAY_WIDTH),
+            usize::from(chip8::DISPLAY_HEIGHT),
+        ],
+        rgba,
+    );
+
+    // nearest neighbour filtering keeps single pixels sharp
+    match texture {
+        Some(texture) => {
+            texture.set(image, egui::TextureOptions::NEAREST);
+            texture.id()
+        }
+        None => texture
+            .insert(ctx.load_texture(name, image, egui::TextureOptions::NEAREST))
+            .id(),
+    }
+}
+
+/// Show a register value, highlighted if it changed with the last update
+fn register_value(ui: &mut Ui, value: String, changed: bool) {
+    let text = egui::RichText::new(value);
+    ui.label(if changed {
+        text.color(ui.visuals().warn_fg_color)
+    } else {
+        text
+    });
+}
+
+/// Checkbox for a quirk, returns true if it was toggled.
+/// Changing quirks in the middle of an instruction sequence is only predictable while paused,
+/// so it is disabled otherwise
+fn quirk_checkbox(
+    ui: &mut Ui,
+    paused: bool,
+    value: &mut bool,
+    text: &str,
+    hover_text: &str,
+) -> bool {
+    ui.add_enabled(paused, egui::Checkbox::new(value, text))
+        .on_hover_text(hover_text)
+        .on_disabled_hover_text("Pause to change quirks")
+        .changed()
+}
+
+/// A small line graph of the stack depth, the newest sample is on the right
+fn stack_depth_graph(ui: &mut Ui, history: &VecDeque<usize>) {
+    let points: egui::plot::PlotPoints = (0_u32..)
+        .zip(history)
+        .map(|(x, depth)| {
+            let depth = u32::try_from(*depth).unwrap_or(u32::MAX);
+            [f64::from(x), f64::from(depth)]
+        })
+        .collect();
+
+    egui::plot::Plot::new("stack_depth_plot")
+        .height(80.0)
+        .include_y(0.0)
+        .show_x(false)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false)
+        .show(ui, |plot_ui| plot_ui.line(egui::plot::Line::new(points)));
+}
+
+/// Remove elements from the front until at most `len` are left
+fn drop_oldest<T>(queue: &mut VecDeque<T>, len: usize) {
+    let excess = queue.len().saturating_sub(len);
+    queue.drain(..excess);
 }
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chip8::{platform::Platform, quirks::Quirks},
+    config,
+    debug_gui::{DisplayOverlay, RenderFilter},
+};
+
+const DEFAULTS_FILE: &str = "defaults.json";
+
+/// Settings for every ROM, saved in the config directory. They take precedence over the built-in
+/// defaults and the detected platform, but command line arguments override them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Defaults {
+    /// used instead of detecting the platform from the ROM
+    pub platform: Option<Platform>,
+    /// used instead of the quirks of the platform, unless `--platform` is given
+    pub quirks: Option<Quirks>,
+    pub overlay: DisplayOverlay,
+    /// see `--filter`
+    pub filter: Option<RenderFilter>,
+    /// see `--max-ipf`
+    pub max_ipf: Option<u32>,
+    /// see `--timer-hz`
+    pub timer_hz: Option<f32>,
+}
+
+impl Defaults {
+    /// Load the saved defaults, falling back to the built-in ones
+    pub fn load() -> Self {
+        match config::load(DEFAULTS_FILE) {
+            Ok(defaults) => defaults.unwrap_or_default(),
+            Err(e) => {
+                log::warn!("Failed to load defaults: {e}");
+                Defaults::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        config::save(DEFAULTS_FILE, self)
+    }
+}
@@ -0,0 +1,67 @@
+use std::time::{Duration, Instant};
+
+/// Limits how often a changed screen is rendered into the framebuffer, for ROMs that draw far
+/// more often than anyone can see. The interpreter keeps running at full speed, draws in between
+/// are not lost but end up in the next rendered frame
+pub struct DrawThrottle {
+    /// the shortest time between two renders, zero renders every change
+    interval: Duration,
+    last_render: Option<Instant>,
+}
+
+impl DrawThrottle {
+    /// Render at most `max_fps` times per second, or every change for None
+    pub fn new(max_fps: Option<u32>) -> Self {
+        DrawThrottle {
+            interval: max_fps
+                .filter(|fps| *fps > 0)
+                .map_or(Duration::ZERO, |fps| Duration::from_secs(1) / fps),
+            last_render: None,
+        }
+    }
+
+    /// Whether a changed screen may be rendered at `now`, counts as a render if it may.
+    /// Only ask when there is something to render, otherwise a render slot is wasted
+    pub fn ready(&mut self, now: Instant) -> bool {
+        if let Some(last_render) = self.last_render {
+            if now.saturating_duration_since(last_render) < self.interval {
+                return false;
+            }
+        }
+
+        self.last_render = Some(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ROM that draws with every instruction is rendered at most `--max-draw-fps` times per
+    /// second, and its last draw is still rendered once the throttle allows it
+    #[test]
+    fn limits_renders() {
+        let start = Instant::now();
+        let instruction_time = Duration::from_secs(1) / 800;
+
+        let mut throttle = DrawThrottle::new(Some(30));
+        let renders = (0..800)
+            .filter(|i| throttle.ready(start + instruction_time * *i))
+            .count();
+        assert!(
+            (1..=30).contains(&renders),
+            "{renders} renders in a second, expected at most 30"
+        );
+        assert!(
+            throttle.ready(start + Duration::from_secs(1) + Duration::from_secs(1) / 30),
+            "The last draw should be rendered after the throttle interval"
+        );
+
+        let mut unlimited = DrawThrottle::new(None);
+        let renders = (0..800)
+            .filter(|i| unlimited.ready(start + instruction_time * *i))
+            .count();
+        assert_eq!(renders, 800, "Renders without a limit");
+    }
+}
@@ -0,0 +1,34 @@
+use std::{
+    io::{BufWriter, Write},
+    sync::mpsc::{self, Sender},
+};
+
+use crate::chip8::instructions::Instruction;
+
+/// Print every executed instruction to stdout as `ADDR: OPCODE  MNEMONIC`, independent of the log level.
+///
+/// Writing happens on a separate thread, so a slow terminal does not stall the emulation.
+/// The output is flushed whenever all received instructions are written
+pub fn spawn() -> Sender<(usize, Instruction)> {
+    let (sender, receiver) = mpsc::channel::<(usize, Instruction)>();
+
+    std::thread::spawn(move || {
+        let mut out = BufWriter::new(std::io::stdout());
+
+        while let Ok(first) = receiver.recv() {
+            for (address, instruction) in std::iter::once(first).chain(receiver.try_iter()) {
+                let opcode = u16::from(instruction);
+                // stdout was closed, e.g. when piping into head
+                if writeln!(out, "{address:03X}: {opcode:04X}  {instruction}").is_err() {
+                    return;
+                }
+            }
+
+            if out.flush().is_err() {
+                return;
+            }
+        }
+    });
+
+    sender
+}
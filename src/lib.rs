@@ -0,0 +1,11 @@
+#![warn(clippy::pedantic)]
+#![warn(clippy::style)]
+#![allow(clippy::too_many_lines)]
+#![allow(clippy::many_single_char_names)]
+#![allow(clippy::must_use_candidate)]
+#![allow(clippy::missing_errors_doc)]
+
+//! The CHIP-8 interpreter core, without any windowing, rendering or debugger UI.
+//! See `examples/run_headless.rs` for a minimal frontend.
+
+pub mod chip8;
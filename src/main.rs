@@ -2,38 +2,79 @@
 #![warn(clippy::style)]
 #![allow(clippy::too_many_lines)]
 #![allow(clippy::many_single_char_names)]
-#![feature(bigint_helper_methods)]
 
-mod chip8;
+#[cfg(feature = "audio")]
+mod audio;
+mod benchmark;
+mod compare;
+mod config;
+mod cycle_timing;
+mod debug_command;
 mod debug_gui;
+mod defaults;
+mod draw_throttle;
+mod instruction_dump;
+mod rom_report;
+mod rom_watcher;
+mod selftest;
+mod session;
+#[cfg(all(unix, feature = "signals"))]
+mod signals;
+mod symbols;
+mod trace;
 
 use std::{
+    collections::VecDeque,
     fs::{self, File},
     io::{Read, Seek},
     os::unix::prelude::FileExt,
-    path::PathBuf,
-    sync::{Arc, Mutex},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     time::{Duration, Instant},
 };
 
-use chip8::Chip8;
+use chip8stuff::chip8::{
+    self,
+    platform::Platform,
+    quirks::{Collision, DrawMode, PcOverflow, Quirks},
+    snapshot::{Change, Snapshot},
+    Chip8,
+};
 use chrono::Utc;
 use clap::Parser;
 
 use log::LevelFilter;
 use pixels::{Pixels, SurfaceTexture};
+use serde::Serialize;
 use simple_logger::SimpleLogger;
 use winit::{
     dpi::LogicalSize,
-    event::{Event, VirtualKeyCode},
+    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::WindowBuilder,
 };
 use winit_input_helper::WinitInputHelper;
 
+#[cfg(feature = "audio")]
+use crate::audio::Audio;
 use crate::{
-    chip8::{instructions::Instruction, Mode},
-    debug_gui::{DebugGui, EguiFramework},
+    chip8::{instructions::Instruction, Mode, StepOutcome},
+    compare::Compare,
+    cycle_timing::CycleTiming,
+    debug_gui::{
+        BreakpointCommand, BreakpointForm, CompareView, DebugGui, DebuggerLayout, DisplayOverlay,
+        EguiFramework, MemoryViewer, Performance, RegisterChanges, RenderFilter, SpriteExport,
+        DEFAULT_INSTRUCTION_HISTORY_LEN,
+    },
+    defaults::Defaults,
+    draw_throttle::DrawThrottle,
+    session::Session,
+    symbols::Symbols,
+    trace::Trace,
 };
 
 // How many pixel we display per vram pixel
@@ -44,6 +85,22 @@ const WINDOW_HEIGHT: u32 = chip8::DISPLAY_HEIGHT as u32 * 10;
 // Instruction cycle frequency
 const TARGET_FREQUENCY: f32 = 800.0; // hz;
 
+// draws that can be undone with "Prev Frame" in the debugger
+const FRAME_HISTORY_LEN: usize = 100;
+
+// sleeping can overshoot by a millisecond or more on many systems, so the last part
+// of the time between two cycles is spent yielding instead
+const SPIN_DURATION: Duration = Duration::from_micros(500);
+
+// how long cycles are averaged before warning that the interpreter falls behind, see --behind-threshold
+const BEHIND_WINDOW: Duration = Duration::from_secs(1);
+
+// how often the event loop checks whether the interpreter changed something that has to be redrawn
+const REDRAW_POLL_INTERVAL: Duration = Duration::from_micros(16_667);
+
+/// Shows and hides the performance HUD of the debugger
+const HUD_KEY: VirtualKeyCode = VirtualKeyCode::F3;
+
 const LOG_TARGET_WINIT_INPUT: &str = "WINIT_INPUT";
 const LOG_TARGET_TIMING: &str = "TIMING";
 const LOG_TARGET_RENDERING: &str = "RENDER";
@@ -72,6 +129,7 @@ const KEY_BINDINGS: [VirtualKeyCode; 16] = [
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[allow(clippy::struct_excessive_bools)]
 struct Args {
     rom_file: Option<String>,
     /// Start interpreter in paused mode
@@ -83,6 +141,119 @@ struct Args {
     /// Create a new standalone executable that includes a copy of the given ROM file
     #[arg(long)]
     embed: Option<String>,
+    /// Reset the interpreter and reload the ROM file whenever it changes on disk
+    #[arg(short, long)]
+    watch: bool,
+    /// Only render the display, without the debugger overlay
+    #[arg(long)]
+    no_gui: bool,
+    /// How the pixels are scaled up: nearest for sharp pixels, or crt for a softer image.
+    /// Nearest unless set in the defaults
+    #[arg(long)]
+    filter: Option<RenderFilter>,
+    /// Platform the ROM was written for, to select its quirks: chip8, schip or xochip.
+    /// Detected from the ROM unless set in the defaults
+    #[arg(long)]
+    platform: Option<Platform>,
+    /// How sprites are combined with the screen: xor (standard), or (never erase pixels) or and
+    #[arg(long)]
+    draw_mode: Option<DrawMode>,
+    /// What happens when the PC runs off the end of memory: error (pause in the debugger, default), halt or wrap
+    #[arg(long)]
+    pc_overflow: Option<PcOverflow>,
+    /// When drawing sets VF: erased (a pixel was turned off, standard) or overlap (a sprite pixel hit a pixel that is on)
+    #[arg(long)]
+    collision: Option<Collision>,
+    /// Shift VX in place in 8XY6/8XYE and ignore VY, like the SCHIP
+    #[arg(long)]
+    shift_vx: bool,
+    /// Wrap sprites around the screen edges instead of clipping them, same as --wrap-x --wrap-y
+    #[arg(long)]
+    wrap_sprites: bool,
+    /// Wrap sprites around the left and right screen edges instead of clipping them
+    #[arg(long)]
+    wrap_x: bool,
+    /// Wrap sprites around the top and bottom screen edges instead of clipping them
+    #[arg(long)]
+    wrap_y: bool,
+    /// Leave I unchanged in FX55/FX65, like the SCHIP
+    #[arg(long)]
+    keep_i: bool,
+    /// Read BNNN as BXNN and jump to XNN + VX, like the SCHIP
+    #[arg(long)]
+    jump_vx: bool,
+    /// Wait for the next timer tick after drawing a sprite, like the original interpreter. Reduces flicker
+    #[arg(long)]
+    display_wait: bool,
+    /// Draw the part of a sprite that is inside of memory instead of failing when it reaches past the end
+    #[arg(long)]
+    clamp_sprite_reads: bool,
+    /// Skip words that are no instruction with a warning instead of pausing, for ROMs that step through data
+    #[arg(long)]
+    skip_bad_opcodes: bool,
+    /// Show a test pattern until the ROM clears the screen or draws something, to check the rendering
+    #[arg(long)]
+    fill_start: bool,
+    /// Stop with an error when I is moved outside of memory, instead of wrapping around at 0xFFFF
+    #[arg(long)]
+    strict_i: bool,
+    /// Key that quits the interpreter: escape, backspace, delete, end, pause, f1 to f12, or none
+    #[arg(long, default_value = "escape")]
+    quit_key: QuitKey,
+    /// Quit right away instead of asking for confirmation in the debugger
+    #[arg(long)]
+    no_confirm_quit: bool,
+    /// Execute at most this many instructions between two timer ticks, unlimited by default
+    #[arg(long)]
+    max_ipf: Option<u32>,
+    /// Render a changed screen at most this many times per second, draws in between end up in the next frame.
+    /// Instructions still run at full speed. Unlimited by default
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    max_draw_fps: Option<u32>,
+    /// How often per second the delay timer is decreased. Only change it to experiment or for
+    /// unusual platforms, ROMs expect 60. 60 unless set in the defaults
+    #[arg(long)]
+    timer_hz: Option<f32>,
+    /// Only warn about falling behind the instruction frequency when cycles take this many percent
+    /// longer than they should, averaged over a second
+    #[arg(long, default_value_t = 10)]
+    behind_threshold: u32,
+    /// Log the inputs, result and VF of every arithmetic instruction, to debug carry and borrow problems
+    #[arg(long)]
+    log_flags: bool,
+    /// Log every instruction that changes VF, with the old and new value
+    #[arg(long)]
+    log_vf: bool,
+    /// Print every executed instruction to stdout as `ADDR: OPCODE  MNEMONIC`, without the other trace logs
+    #[arg(long)]
+    dump_instructions: bool,
+    /// How many of the last executed instructions are printed to stderr when an instruction fails
+    /// or the interpreter panics, 0 disables it
+    #[arg(long, default_value_t = 32)]
+    trace_len: usize,
+    /// Save the interpreter state to a JSON file when the window is closed
+    #[arg(long)]
+    dump_state_on_exit: bool,
+    /// Continue a debugging session saved from the debugger
+    #[arg(long)]
+    session: Option<String>,
+    /// Run a generated ROM that exercises every instruction without opening a window, and print the timing
+    #[arg(long)]
+    selftest: bool,
+    /// Print the size, checksum, detected platform and opcode statistics of a ROM without opening a window
+    #[arg(long, value_name = "ROM_FILE")]
+    info: Option<String>,
+    /// Run the ROM, or the self test ROM if none is given, as fast as possible and print the achieved speed
+    #[arg(long)]
+    benchmark: bool,
+    /// Run a second interpreter with this ROM in lockstep and pause where their states differ first.
+    /// Give the same ROM and --compare-platform to compare quirks
+    #[arg(long)]
+    compare: Option<String>,
+    /// Platform of the compared interpreter, which selects its quirks and memory size.
+    /// It uses the same as the main interpreter by default
+    #[arg(long, requires = "compare")]
+    compare_platform: Option<Platform>,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -106,12 +277,46 @@ fn main() -> anyhow::Result<()> {
         .with_module_level(chip8::LOG_TARGET_INSTRUCTIONS, log_level)
         .with_module_level(chip8::LOG_TARGET_DRAWING, log_level)
         .with_module_level(chip8::LOG_TARGET_TIMER, log_level)
+        .with_module_level(chip8::LOG_TARGET_SOUND, log_level)
+        .with_module_level(chip8::LOG_TARGET_VBLANK, log_level)
+        .with_module_level(chip8::LOG_TARGET_DEBUGGER, log_level)
+        .with_module_level(chip8::LOG_TARGET_FLAGS, log_level)
         // interpreter log targets
         .with_module_level(LOG_TARGET_RENDERING, log_level)
         .with_module_level(LOG_TARGET_TIMING, log_level)
         .with_module_level(LOG_TARGET_WINIT_INPUT, log_level)
+        .with_module_level(rom_watcher::LOG_TARGET_WATCH, log_level)
         .init()?;
 
+    if args.selftest {
+        return selftest::run();
+    }
+
+    #[cfg(all(unix, feature = "signals"))]
+    signals::install()?;
+
+    if let Some(rom_file) = &args.info {
+        return rom_report::run(rom_file);
+    }
+
+    // settings saved from the debugger, the command line takes precedence
+    let defaults = Defaults::load();
+
+    let timer_hz = args
+        .timer_hz
+        .or(defaults.timer_hz)
+        .unwrap_or(chip8::DELAY_TIMER_FREQUENCY);
+    anyhow::ensure!(
+        timer_hz > 0.0 && timer_hz <= TARGET_FREQUENCY,
+        "--timer-hz has to be above 0 and at most the instruction frequency of {TARGET_FREQUENCY} Hz"
+    );
+    let cycles_per_timer_tick = chip8::cycles_per_timer_tick(TARGET_FREQUENCY, timer_hz);
+    let filter = args.filter.or(defaults.filter).unwrap_or_default();
+
+    if args.benchmark {
+        return benchmark::run(args.rom_file.as_deref(), cycles_per_timer_tick);
+    }
+
     if let Some(rom_file) = args.embed {
         log::info!("Embedding {rom_file}");
 
@@ -121,7 +326,7 @@ fn main() -> anyhow::Result<()> {
         let exe_path = std::env::current_exe()?;
 
         let p = PathBuf::from(rom_file);
-        let rom_name = p.file_name().unwrap().to_str().unwrap().clone();
+        let rom_name = p.file_name().unwrap().to_str().unwrap();
         let new_exe_name = format!("chip8stuff_{rom_name}_player");
 
         fs::copy(exe_path, &new_exe_name)?;
@@ -137,6 +342,7 @@ fn main() -> anyhow::Result<()> {
         log::info!("Done");
         log::info!("Writing trailer ");
 
+        #[allow(clippy::cast_possible_truncation)]
         exe.write_all_at(
             &[
                 EMBEDDED_ROM_TRAILER_MAGIC,
@@ -153,25 +359,140 @@ fn main() -> anyhow::Result<()> {
         return Ok(());
     }
 
-    let mut chip8 = Chip8::new();
+    let platform = args.platform.or(defaults.platform);
+
+    // without a given platform, the memory size is adjusted after detecting it from the ROM
+    let mut chip8 = match platform {
+        Some(platform) => Chip8::with_memory_size(platform.memory_size())?,
+        None => Chip8::new(),
+    };
+    chip8.log_flags = args.log_flags;
+    chip8.log_vf_changes = args.log_vf;
+
+    let session = args.session.as_ref().map(Session::load).transpose()?;
+
+    if let Some(session) = &session {
+        chip8.restore(&session.state)?;
+        chip8.quirks = session.quirks;
+        for condition in &session.breakpoints {
+            chip8.add_breakpoint(*condition);
+        }
+        log::info!("Restored session {}", args.session.as_ref().unwrap());
+    }
 
     if args.paused {
         chip8.mode = Mode::Paused;
     }
 
-    // If a file path is passed, load the rom
-    if let Some(rom_file) = args.rom_file {
-        chip8.load_rom(&rom_file)?;
-        log::info!("Loaded rom file {}", rom_file);
-    } else {
-        // if there is no rom to load, check if there is a rom embedded in the executable
-        load_embedded_rom(&mut chip8)?;
+    // a restored session already has something on the screen
+    if args.fill_start && session.is_none() {
+        chip8.fill_test_pattern();
     }
 
+    // a restored session keeps its memory, including the ROM, and its quirks
+    if session.is_none() {
+        let (rom, rom_name) = if let Some(rom_file) = &args.rom_file {
+            // If a file path is passed, load the rom
+            let rom_name = Path::new(rom_file).file_name().map_or_else(
+                || rom_file.clone(),
+                |name| name.to_string_lossy().into_owned(),
+            );
+            (std::fs::read(rom_file)?, rom_name)
+        } else {
+            // if there is no rom to load, check if there is a rom embedded in the executable
+            (embedded_rom()?, "embedded ROM".to_owned())
+        };
+
+        // the memory has to have the size of the platform before loading, a ROM for a platform
+        // with more memory doesn't fit otherwise
+        let platform = platform.unwrap_or_else(|| {
+            let platform = Platform::detect(&rom);
+            log::info!("Detected platform {platform}, use --platform to override");
+            platform
+        });
+        chip8.set_memory_size(platform.memory_size())?;
+
+        chip8.load_rom_bytes(&rom)?;
+        chip8.set_rom_name(&rom_name);
+        log::info!("Loaded rom {rom_name}");
+
+        if let Some(address) = chip8.early_return() {
+            log::warn!(
+                "The ROM returns from a subroutine at 0x{address:X} before calling one. \
+                 It might be misaligned or meant to be loaded at another address than 0x{:X}",
+                chip8::PC_INIT
+            );
+        }
+
+        // the quirks of a platform given on the command line win over the saved quirks
+        chip8.quirks = match defaults.quirks {
+            Some(quirks) if args.platform.is_none() => quirks,
+            _ => platform.quirks(),
+        };
+        if let Some(draw_mode) = args.draw_mode {
+            chip8.quirks.draw_mode = draw_mode;
+        }
+        if let Some(collision) = args.collision {
+            chip8.quirks.collision = collision;
+        }
+        if let Some(pc_overflow) = args.pc_overflow {
+            chip8.quirks.pc_overflow = pc_overflow;
+        }
+        if args.shift_vx {
+            chip8.quirks.shift_uses_vy = false;
+        }
+        if args.wrap_sprites || args.wrap_x {
+            chip8.quirks.wrap_x = true;
+        }
+        if args.wrap_sprites || args.wrap_y {
+            chip8.quirks.wrap_y = true;
+        }
+        if args.keep_i {
+            chip8.quirks.increment_i_on_store = false;
+        }
+        if args.jump_vx {
+            chip8.quirks.jump_v0_uses_vx = true;
+        }
+        if args.display_wait {
+            chip8.quirks.display_wait = true;
+        }
+        if args.clamp_sprite_reads {
+            chip8.quirks.clamp_sprite_reads = true;
+        }
+        if args.skip_bad_opcodes {
+            chip8.quirks.skip_unknown_opcodes = true;
+        }
+        if args.strict_i {
+            chip8.quirks.wrap_address_register = false;
+        }
+    }
+    if let Some(rom_info) = chip8.rom_info() {
+        log::info!("ROM: {rom_info}");
+    }
+
+    // the second interpreter of --compare
+    let compare = if let Some(rom_file) = &args.compare {
+        let (memory_size, quirks) = match args.compare_platform {
+            Some(platform) => (platform.memory_size(), platform.quirks()),
+            None => (chip8.memory.len(), chip8.quirks),
+        };
+        let mut compared = Chip8::with_memory_size(memory_size)?;
+        compared.quirks = quirks;
+        compared.load_rom(rom_file)?;
+        if args.fill_start && session.is_none() {
+            compared.fill_test_pattern();
+        }
+        log::info!("Comparing with {rom_file}, quirks: {:?}", compared.quirks);
+
+        Some(Arc::new(Mutex::new(Compare::new(compared))))
+    } else {
+        None
+    };
+
     let event_loop = EventLoop::new();
     let mut input = WinitInputHelper::new();
     let window = {
-        let size = LogicalSize::new(WINDOW_WIDTH as f64, WINDOW_HEIGHT as f64);
+        let size = LogicalSize::new(f64::from(WINDOW_WIDTH), f64::from(WINDOW_HEIGHT));
         WindowBuilder::new()
             .with_title("CHIP8")
             .with_inner_size(size)
@@ -186,19 +507,31 @@ fn main() -> anyhow::Result<()> {
         Pixels::new(WINDOW_WIDTH, WINDOW_HEIGHT, surface_texture)?
     };
 
-    let mut framework = EguiFramework::new(
-        &event_loop,
-        WINDOW_WIDTH,
-        WINDOW_HEIGHT,
-        window.scale_factor() as f32,
-        &pixels,
-    );
+    #[allow(clippy::cast_possible_truncation)]
+    let mut framework = (!args.no_gui).then(|| {
+        EguiFramework::new(
+            &event_loop,
+            WINDOW_WIDTH,
+            WINDOW_HEIGHT,
+            window.scale_factor() as f32,
+            &pixels,
+        )
+    });
 
-    let framebuffer = [0_u8; (WINDOW_WIDTH * WINDOW_HEIGHT) as usize * 4];
+    let mut framebuffer = vec![0_u8; (WINDOW_WIDTH * WINDOW_HEIGHT) as usize * 4];
+    render_vram(
+        &chip8.vram,
+        &mut framebuffer,
+        DISPLAY_WINDOW_SCALE,
+        defaults.overlay,
+        filter,
+    );
 
     let time_per_instruction: Duration = Duration::from_secs_f32(1.0 / TARGET_FREQUENCY);
 
-    let mut delay_timer_decrease_counter = 0;
+    if framework.is_some() {
+        chip8.frame_history_len = FRAME_HISTORY_LEN;
+    }
 
     let chip8 = Arc::new(Mutex::new(chip8));
 
@@ -211,160 +544,690 @@ fn main() -> anyhow::Result<()> {
 
     let (new_mode_sender, new_mode_receiver) = std::sync::mpsc::channel();
     let (step_sender, step_receiver) = std::sync::mpsc::channel::<()>();
+    let (step_tick_sender, step_tick_receiver) = std::sync::mpsc::channel::<()>();
+    let (previous_frame_sender, previous_frame_receiver) = std::sync::mpsc::channel::<()>();
+    let (step_timers_sender, step_timers_receiver) = std::sync::mpsc::channel::<bool>();
     let (instructions_sender, instructions_receiver) = std::sync::mpsc::channel::<Instruction>();
+    // nobody would receive the executed instructions without the debugger
+    let instructions_sender = framework.is_some().then_some(instructions_sender);
+    let (performance_sender, performance_receiver) = std::sync::mpsc::channel::<Performance>();
+    // whether the buzzer should sound, only sent when it changes
+    let (sound_sender, sound_receiver) = std::sync::mpsc::channel::<bool>();
+    // nobody would play the beep without the audio feature
+    let sound_sender = cfg!(feature = "audio").then_some(sound_sender);
+    #[cfg(not(feature = "audio"))]
+    drop(sound_receiver);
+    let performance_sender = framework.is_some().then_some(performance_sender);
+    let instruction_dump_sender = args.dump_instructions.then(instruction_dump::spawn);
+    let trace = (args.trace_len > 0).then(|| Arc::new(Mutex::new(Trace::new(args.trace_len))));
+    if let Some(trace) = &trace {
+        let trace = trace.clone();
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            // the panicking thread might hold the lock, don't wait for it
+            if let Ok(trace) = trace.try_lock() {
+                trace.dump();
+            }
+            default_hook(info);
+        }));
+    }
     let (dump_memory_sender, dump_memory_receiver) = std::sync::mpsc::channel::<()>();
+    let (rom_reloaded_sender, rom_reloaded_receiver) = std::sync::mpsc::channel::<()>();
+    let (breakpoint_sender, breakpoint_receiver) = std::sync::mpsc::channel();
+    let (save_session_sender, save_session_receiver) = std::sync::mpsc::channel::<()>();
+    let (quirks_sender, quirks_receiver) = std::sync::mpsc::channel::<Quirks>();
+    let (overlay_sender, overlay_receiver) = std::sync::mpsc::channel::<DisplayOverlay>();
+    let (filter_sender, filter_receiver) = std::sync::mpsc::channel::<RenderFilter>();
+    let (freeze_display_sender, freeze_display_receiver) = std::sync::mpsc::channel::<bool>();
+    let (capture_frame_sender, capture_frame_receiver) = std::sync::mpsc::channel::<()>();
+    let (set_register_sender, set_register_receiver) = std::sync::mpsc::channel::<(usize, u8)>();
+    let (step_changes_sender, step_changes_receiver) = std::sync::mpsc::channel::<Vec<Change>>();
+
+    // set by the interpreter thread when the window shows something outdated: the framebuffer
+    // was rendered, the mode changed, or the debugger shows state that changed
+    let redraw_needed = Arc::new(AtomicBool::new(true));
 
     std::thread::spawn({
         let chip8 = chip8.clone();
+        let compare = compare.clone();
+        let trace = trace.clone();
         let framebuffer = framebuffer.clone();
+        let redraw_needed = redraw_needed.clone();
+        // whether single steps also advance the timers
+        let mut step_timers = false;
+        // used to log the achieved cycle frequency once per second
+        let mut cycles = 0;
+        let mut cycles_counted_since = Instant::now();
+        // for the performance HUD of the debugger, counted like the cycles
+        let mut instructions: u32 = 0;
+        let mut frames_counted_since = chip8.lock().unwrap().frame_count();
+        let mut sound_playing = false;
+        // the framebuffer was rendered before the thread started
+        let mut rendered_vram_version = chip8.lock().unwrap().vram_version;
+        // instructions executed since the last timer tick, limited by --max-ipf
+        let mut instructions_this_frame = 0;
+        let max_ipf = args.max_ipf.or(defaults.max_ipf).unwrap_or(u32::MAX);
+        let mut cycle_timing =
+            CycleTiming::new(time_per_instruction, args.behind_threshold, BEHIND_WINDOW);
+        let mut overlay = defaults.overlay;
+        let mut filter = filter;
+        let mut draw_throttle = DrawThrottle::new(args.max_draw_fps);
+        // the framebuffer keeps the last rendered frame while frozen
+        let mut display_frozen = false;
+        // set by Step Tick: the state before it and the instructions executed since.
+        // Until the next timer tick, time passes like while running, one instruction per pass
+        let mut tick_step: Option<(Snapshot, u32)> = None;
+        let mut last_mode = {
+            let chip8 = chip8.lock().unwrap();
+            (chip8.mode, chip8.waiting_for_key)
+        };
+
         move || loop {
             let last_cycle_finished = Instant::now();
             let mut chip8 = chip8.lock().unwrap();
-            chip8.redraw = false;
 
             if let Ok(new_mode) = new_mode_receiver.try_recv() {
+                log::trace!(target: chip8::LOG_TARGET_DEBUGGER, "switching mode from {:?} to {new_mode:?}", chip8.mode);
                 chip8.mode = new_mode;
             }
 
+            #[cfg(all(unix, feature = "signals"))]
+            {
+                if signals::take_toggle_pause() {
+                    chip8.mode = match chip8.mode {
+                        Mode::Running => Mode::Paused,
+                        Mode::Paused => Mode::Running,
+                        Mode::Halted => Mode::Halted,
+                    };
+                    log::info!("Received SIGUSR1, mode is now {:?}", chip8.mode);
+                }
+                if signals::take_dump_state() {
+                    match dump_state(&chip8) {
+                        Ok(p) => log::info!("Received SIGUSR2, saved interpreter state to {p}"),
+                        Err(e) => log::error!("Failed to save interpreter state: {e}"),
+                    }
+                }
+            }
+
+            for command in breakpoint_receiver.try_iter() {
+                match command {
+                    BreakpointCommand::Add(condition) => chip8.add_breakpoint(condition),
+                    BreakpointCommand::Remove(condition) => chip8.remove_breakpoint(condition),
+                    BreakpointCommand::RunTo(address) => chip8.run_to(address),
+                }
+            }
+
+            if previous_frame_receiver.try_recv().is_ok() {
+                match chip8.previous_frame() {
+                    Ok(true) => {
+                        log::trace!(target: chip8::LOG_TARGET_DEBUGGER, "went back to 0x{:X}", chip8.pc);
+                    }
+                    Ok(false) => {
+                        log::info!(target: chip8::LOG_TARGET_DEBUGGER, "No earlier draw to go back to");
+                    }
+                    Err(e) => log::error!("Failed to go back a frame: {e}"),
+                }
+            }
+
             if dump_memory_receiver.try_recv().is_ok() {
                 let p = format!("memory_dump_{}.bin", Utc::now());
 
-                std::fs::write(&p, chip8.memory).unwrap();
+                std::fs::write(&p, &chip8.memory).unwrap();
                 log::info!("Saved memory to {p}");
             }
 
+            for (register, value) in set_register_receiver.try_iter() {
+                log::trace!(target: chip8::LOG_TARGET_DEBUGGER, "setting V{register:X} to 0x{value:X}");
+                chip8.registers[register] = value;
+            }
+
+            if let Ok(quirks) = quirks_receiver.try_recv() {
+                log::trace!(target: chip8::LOG_TARGET_DEBUGGER, "changing quirks to {quirks:?}");
+                chip8.quirks = quirks;
+            }
+
+            if let Ok(enabled) = step_timers_receiver.try_recv() {
+                log::trace!(target: chip8::LOG_TARGET_DEBUGGER, "stepping timers: {enabled}");
+                step_timers = enabled;
+            }
+
+            // the timers count this pass by the mode it started in
+            let mode = chip8.mode;
+            let paused = mode == Mode::Paused;
+            let waiting_for_key = chip8.waiting_for_key.is_some();
+            let mut executed = false;
+
+            if !paused {
+                tick_step = None;
+            } else if tick_step.is_none() && step_tick_receiver.try_recv().is_ok() {
+                tick_step = Some((chip8.snapshot(), 0));
+            }
+            let stepping_tick = tick_step.is_some();
+
             if chip8.mode == Mode::Running
-                // if we are paused, wait until the next step is executed via debugger
-                || chip8.mode == Mode::Paused && step_receiver.try_recv().is_ok()
+                && !waiting_for_key
+                && !chip8.waiting_for_vblank
+                && instructions_this_frame < max_ipf
+                // a timer tick step waits for a key or the display like a running interpreter
+                || stepping_tick && !waiting_for_key && !chip8.waiting_for_vblank
+                // if we are paused, wait until the next step is executed via debugger.
+                // Steps do nothing until the key FX0A waits for is released, but don't wait for the display
+                || paused && !stepping_tick && step_receiver.try_recv().is_ok() && !waiting_for_key
             {
-                let instruction = chip8.step_cycle().unwrap();
-                instructions_sender.send(instruction).unwrap();
-
-                // decrease the 60hz timer every x instructions, depending on our instruction execution frequency
-                delay_timer_decrease_counter += 1;
-                if delay_timer_decrease_counter
-                    == (TARGET_FREQUENCY / chip8::DELAY_TIMER_FREQUENCY).floor() as i32
-                {
-                    if chip8.delay_timer > 0 {
-                        chip8.delay_timer -= 1;
+                let pc = chip8.pc;
+                // single steps show what they changed in the debugger, a timer tick step shows
+                // the changes of the whole tick
+                let before_step = (paused && !stepping_tick).then(|| chip8.snapshot());
+                let mut compare = compare.as_ref().map(|compare| compare.lock().unwrap());
+                if let Some(compare) = &mut compare {
+                    compare.sync_input(&chip8);
+                }
+                if let Some(trace) = &trace {
+                    trace.lock().unwrap().record(&chip8);
+                }
+                match chip8.step_cycle() {
+                    Ok(StepOutcome { instruction, .. }) => {
+                        if let Some(compare) = &mut compare {
+                            if compare.step(&chip8) {
+                                let divergence = compare.divergence.as_ref().unwrap();
+                                log::warn!(
+                                    "The compared interpreter differs after {} instructions, at 0x{pc:X} {instruction}",
+                                    divergence.cycle
+                                );
+                                if let Some(error) = &divergence.error {
+                                    log::warn!("The compared interpreter failed: {error}");
+                                }
+                                for change in &divergence.changes {
+                                    log::warn!("  {change}");
+                                }
+                                chip8.mode = Mode::Paused;
+                            }
+                        }
+                        if let Some(before_step) = before_step {
+                            step_changes_sender
+                                .send(before_step.diff(&chip8.snapshot()))
+                                .unwrap();
+                        }
+                        if let Some(instructions_sender) = &instructions_sender {
+                            instructions_sender.send(instruction).unwrap();
+                            // the debugger shows the registers and executed instructions
+                            redraw_needed.store(true, Ordering::Relaxed);
+                        }
+                        if let Some(instruction_dump_sender) = &instruction_dump_sender {
+                            // the dump thread stops when stdout is closed, keep running without it
+                            let _ = instruction_dump_sender.send((pc, instruction));
+                        }
+                    }
+                    // pause instead of crashing, so the state can still be inspected in the debugger.
+                    // The failed instruction is skipped when continuing
+                    Err(e) if chip8.mode == Mode::Halted => {
+                        log::warn!("{e}");
+                        if let Some(trace) = &trace {
+                            trace.lock().unwrap().dump();
+                        }
                     }
-                    delay_timer_decrease_counter = 0;
+                    Err(e) => {
+                        log::error!("Failed to execute the instruction at 0x{pc:X}: {e}");
+                        if let Some(trace) = &trace {
+                            trace.lock().unwrap().dump();
+                        }
+                        chip8.mode = Mode::Paused;
+                        tick_step = None;
+                    }
+                }
+                instructions_this_frame += 1;
+                instructions += 1;
+                executed = true;
+                if let Some((_, tick_instructions)) = &mut tick_step {
+                    *tick_instructions += 1;
                 }
+            }
 
-                if chip8.redraw {
-                    log::trace!(target: LOG_TARGET_RENDERING, "rendering into framebuffer");
-                    let mut f = framebuffer.lock().unwrap();
-                    render_vram(&chip8.vram, &mut *f);
+            let overlay_changed = match overlay_receiver.try_recv() {
+                Ok(new_overlay) => {
+                    overlay = new_overlay;
+                    true
+                }
+                Err(_) => false,
+            };
+            let filter_changed = match filter_receiver.try_recv() {
+                Ok(new_filter) => {
+                    filter = new_filter;
+                    true
                 }
-                chip8.redraw = false;
+                Err(_) => false,
+            };
+
+            if let Some(frozen) = freeze_display_receiver.try_iter().last() {
+                log::trace!(target: LOG_TARGET_RENDERING, "display frozen: {frozen}");
+                display_frozen = frozen;
             }
+            let capture_frame = capture_frame_receiver.try_iter().count() > 0;
+
+            // a throttled change stays pending until the throttle allows the next render
+            let vram_changed =
+                chip8.vram_version != rendered_vram_version && draw_throttle.ready(Instant::now());
 
-            // decrease the 60hz timer every x instructions, depending on our instruction execution frequency
-            delay_timer_decrease_counter += 1;
-            if delay_timer_decrease_counter
-                == (TARGET_FREQUENCY / chip8::DELAY_TIMER_FREQUENCY).floor() as i32
+            // instructions are executed while holding the lock, so the vram always contains complete sprites here
+            if (vram_changed || overlay_changed || filter_changed) && !display_frozen
+                || capture_frame
             {
-                if chip8.delay_timer > 0 {
-                    chip8.delay_timer -= 1;
+                log::trace!(target: LOG_TARGET_RENDERING, "rendering into framebuffer");
+                let mut f = framebuffer.lock().unwrap();
+                render_vram(&chip8.vram, &mut f, DISPLAY_WINDOW_SCALE, overlay, filter);
+                rendered_vram_version = chip8.vram_version;
+                redraw_needed.store(true, Ordering::Relaxed);
+            }
+
+            // the window title and the debugger show the mode
+            if (chip8.mode, chip8.waiting_for_key) != last_mode {
+                last_mode = (chip8.mode, chip8.waiting_for_key);
+                redraw_needed.store(true, Ordering::Relaxed);
+            }
+
+            // decrease the timer every x passes, depending on our instruction execution frequency.
+            // Single steps only count if enabled in the debugger, otherwise the timers are frozen while paused
+            // a timer tick step lets time pass like a running interpreter
+            let timer_mode = if stepping_tick { Mode::Running } else { mode };
+            if chip8.count_loop_cycle(timer_mode, executed, step_timers, cycles_per_timer_tick) {
+                instructions_this_frame = 0;
+
+                if let Some((before_tick, tick_instructions)) = tick_step.take() {
+                    log::info!(target: chip8::LOG_TARGET_DEBUGGER, "Executed {tick_instructions} instructions until the next timer tick");
+                    step_changes_sender
+                        .send(before_tick.diff(&chip8.snapshot()))
+                        .unwrap();
+                    redraw_needed.store(true, Ordering::Relaxed);
                 }
-                delay_timer_decrease_counter = 0;
             }
 
+            // the sound timer is frozen while paused, the beep shouldn't go on and on
+            let sound = chip8.mode == Mode::Running && chip8.is_sound_playing();
+            if sound != sound_playing {
+                sound_playing = sound;
+                if let Some(sound_sender) = &sound_sender {
+                    sound_sender.send(sound).unwrap();
+                }
+            }
+
+            let frame_count = chip8.frame_count();
             drop(chip8);
 
+            cycles += 1;
+            if cycles_counted_since.elapsed() >= Duration::from_secs(1) {
+                let elapsed = cycles_counted_since.elapsed().as_secs_f64();
+                log::debug!(target: LOG_TARGET_TIMING, "{:.0} cycles/s", f64::from(cycles) / elapsed);
+                if let Some(performance_sender) = &performance_sender {
+                    // a second has far fewer frames than f64 can count exactly
+                    #[allow(clippy::cast_precision_loss)]
+                    let frames = (frame_count - frames_counted_since) as f64;
+                    performance_sender
+                        .send(Performance::new(f64::from(instructions), frames, elapsed))
+                        .unwrap();
+                    redraw_needed.store(true, Ordering::Relaxed);
+                }
+                cycles = 0;
+                instructions = 0;
+                frames_counted_since = frame_count;
+                cycles_counted_since = Instant::now();
+            }
+
             // wait for some time so we can operate at our target frequency
-            if last_cycle_finished.elapsed() < time_per_instruction {
-                let time_left = time_per_instruction - last_cycle_finished.elapsed();
+            if let Some(time_left) = time_per_instruction.checked_sub(last_cycle_finished.elapsed())
+            {
                 log::trace!(target: LOG_TARGET_TIMING, "Sleeping for {time_left:?}");
-                std::thread::sleep(time_left);
+                wait_until(last_cycle_finished + time_per_instruction);
             } else {
-                log::warn!(target:LOG_TARGET_TIMING, "Instruction execution took {:?}, falling behind our target execution frequency", last_cycle_finished.elapsed());
+                log::trace!(target: LOG_TARGET_TIMING, "Instruction execution took {:?}, falling behind our target execution frequency", last_cycle_finished.elapsed());
+            }
+
+            if let Some((average, cycles)) = cycle_timing.record(last_cycle_finished.elapsed()) {
+                log::warn!(target: LOG_TARGET_TIMING, "Cycles took {average:?} on average over the last {cycles} cycles instead of {time_per_instruction:?}, falling behind our target execution frequency");
             }
         }
     });
 
+    if args.watch {
+        if let Some(rom_file) = &args.rom_file {
+            rom_watcher::watch_rom(rom_file, {
+                let chip8 = chip8.clone();
+                move |rom| {
+                    // keep the memory size of the platform
+                    let memory_size = chip8.lock().unwrap().memory.len();
+                    let mut new_chip8 = Chip8::with_memory_size(memory_size)
+                        .expect("the memory size of the running interpreter is valid");
+                    if let Err(e) = new_chip8.load_rom_bytes(&rom) {
+                        log::error!("Failed to reload rom: {e}");
+                        return;
+                    }
+
+                    let mut chip8 = chip8.lock().unwrap();
+                    if let Some(name) = chip8.rom_info().and_then(|info| info.name.as_deref()) {
+                        new_chip8.set_rom_name(name);
+                    }
+                    // keep the debugger paused, but don't wait for a key the new rom never asked for
+                    if chip8.mode == Mode::Paused {
+                        new_chip8.mode = Mode::Paused;
+                    }
+                    new_chip8.quirks = chip8.quirks;
+                    new_chip8.log_flags = chip8.log_flags;
+                    new_chip8.log_vf_changes = chip8.log_vf_changes;
+                    new_chip8.frame_history_len = chip8.frame_history_len;
+                    // make sure the emulator thread renders the new (empty) screen
+                    new_chip8.vram_version = chip8.vram_version.wrapping_add(1);
+                    for condition in chip8.breakpoints() {
+                        new_chip8.add_breakpoint(condition);
+                    }
+                    *chip8 = new_chip8;
+
+                    rom_reloaded_sender.send(()).unwrap();
+                }
+            })?;
+        } else {
+            log::warn!("--watch only works with a ROM file, ignoring it");
+        }
+    }
+
+    // embedded ROMs have no file
+    let rom_file = args
+        .rom_file
+        .clone()
+        .or_else(|| session.as_ref().and_then(|s| s.rom_file.clone()));
+
+    // shown in the window title
+    let rom_name = rom_file
+        .as_ref()
+        .map(|rom_file| match Path::new(rom_file).file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => rom_file.clone(),
+        });
+    let mut current_title = String::new();
+
+    let symbols = match &rom_file {
+        Some(rom_file) => Symbols::for_rom(rom_file).unwrap_or_else(|e| {
+            log::warn!("Failed to load symbols: {e}");
+            Symbols::default()
+        }),
+        None => Symbols::default(),
+    };
+
     let c = chip8.lock().unwrap();
-    let mut debug_gui = DebugGui {
+    let mut debug_gui = framework.is_some().then(|| DebugGui {
         chip8_mode: c.mode,
-        show_registers: false,
+        waiting_for_key: c.waiting_for_key,
+        layout: session.map_or_else(DebuggerLayout::load, |session| session.layout),
         registers: c.registers,
         set_mode: new_mode_sender,
         step_sender,
-        instruction_history: Vec::new(),
-        show_instruction_history_window: false,
+        step_tick_sender,
+        previous_frame_sender,
+        step_timers: false,
+        step_timers_sender,
+        instruction_history: VecDeque::new(),
+        instruction_history_len: DEFAULT_INSTRUCTION_HISTORY_LEN,
         pc: c.pc,
         address_register: c.address_register,
+        register_changes: RegisterChanges::default(),
         dump_memory_sender,
-    };
+        rom_reloaded_at: None,
+        breakpoints: Vec::new(),
+        breakpoint_sender,
+        breakpoint_form: BreakpointForm::default(),
+        save_session_sender,
+        quirks: c.quirks,
+        quirks_sender,
+        memory: c.memory.clone(),
+        written_memory: c.written_memory().to_vec(),
+        memory_viewer: MemoryViewer::default(),
+        symbols,
+        overlay: defaults.overlay,
+        overlay_sender,
+        filter,
+        filter_sender,
+        freeze_display: false,
+        freeze_display_sender,
+        capture_frame_sender,
+        command: String::new(),
+        command_error: None,
+        show_quit_dialog: false,
+        quit_confirmed: false,
+        set_register_sender,
+        step_changes: None,
+        preview: vec![0; chip8::DISPLAY_WIDTH as usize * chip8::DISPLAY_HEIGHT as usize * 4],
+        preview_texture: None,
+        vram: c.vram.to_vec(),
+        sprite_export: SpriteExport::default(),
+        compare: compare.is_some().then(CompareView::default),
+        keys: c.keys(),
+        virtual_keys: 0,
+        rom_info: c.rom_info().cloned(),
+        call_stats: c.call_stats(),
+        stack_depth_history: VecDeque::new(),
+        show_hud: false,
+        performance: Performance::default(),
+    });
     drop(c);
 
+    // keys down on the keyboard and in the keypad window of the debugger. The interpreter sees
+    // a key as down while either holds it, so only changes of both together are queued
+    let mut physical_keys = 0_u16;
+    let mut virtual_keys = 0_u16;
+
+    // the debugger asked to be drawn again at this time, e.g. to hide a notice
+    let mut gui_repaint_at: Option<Instant> = None;
+    // input or other window events changed what the debugger shows
+    let mut gui_changed = false;
+    // used to log how often the window is redrawn
+    let mut redraws = 0;
+    let mut redraws_counted_since = Instant::now();
+
+    // the audio stream has to stay on this thread
+    #[cfg(feature = "audio")]
+    let mut audio = match Audio::new() {
+        Ok(audio) => Some(audio),
+        Err(e) => {
+            log::warn!("Sound is disabled: {e}");
+            None
+        }
+    };
+
     event_loop.run(move |event, _, control_flow| {
+        #[cfg(feature = "audio")]
+        for playing in sound_receiver.try_iter() {
+            match &audio {
+                Some(audio) if playing => audio.start(),
+                Some(audio) => audio.stop(),
+                None => {}
+            }
+        }
+
         // Handle input events
         if input.update(&event) {
             // Close events
-            if input.key_pressed(VirtualKeyCode::Escape) || input.close_requested() {
-                *control_flow = ControlFlow::Exit;
-                return;
+            let mut quit = false;
+            if args.quit_key.0.is_some_and(|key| input.key_pressed(key)) || input.close_requested() {
+                match &mut debug_gui {
+                    Some(debug_gui) if !args.no_confirm_quit => debug_gui.show_quit_dialog = true,
+                    _ => quit = true,
+                }
             }
 
-            KEY_BINDINGS.iter().enumerate().for_each(|(i, key)| {
-                let mut chip8 = chip8.lock().unwrap();
-
-                if input.key_pressed(*key) {
-                    chip8.keyboard.set_down(u8::try_from(i).unwrap());
+            if quit || debug_gui.as_ref().is_some_and(|d| d.quit_confirmed) {
+                if let Some(warning) = chip8.lock().unwrap().call_stats().unbalanced_warning() {
+                    log::warn!("{warning}");
+                }
 
-                    log::trace!(target: LOG_TARGET_WINIT_INPUT, "key down: 0x{i:X}");
-                } else if input.key_released(*key) {
-                    chip8.keyboard.set_up(u8::try_from(i).unwrap());
+                if args.dump_state_on_exit {
+                    match dump_state(&chip8.lock().unwrap()) {
+                        Ok(p) => log::info!("Saved interpreter state to {p}"),
+                        Err(e) => log::error!("Failed to save interpreter state: {e}"),
+                    }
+                }
 
-                    if let Mode::WaitForKey { register } = chip8.mode {
-                        chip8.registers[register] = u8::try_from(i).unwrap();
-                        chip8.mode = Mode::Running;
+                if let Some(debug_gui) = &debug_gui {
+                    if let Err(e) = debug_gui.layout.save() {
+                        log::error!("Failed to save debugger layout: {e}");
                     }
+                }
 
-                    log::trace!(target: LOG_TARGET_WINIT_INPUT, "key up: 0x{i:X}");
+                // close the audio stream before the window goes away
+                #[cfg(feature = "audio")]
+                drop(audio.take());
+
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+
+            // key events were queued in the order they arrived, see below
+            chip8.lock().unwrap().process_key_events();
+
+            if let Some(debug_gui) = &mut debug_gui {
+                if input.key_pressed(HUD_KEY) {
+                    debug_gui.show_hud = !debug_gui.show_hud;
+                    gui_changed = true;
                 }
-            });
+            }
 
             // Update the scale factor
-            if let Some(scale_factor) = input.scale_factor() {
+            if let (Some(scale_factor), Some(framework)) = (input.scale_factor(), &mut framework) {
                 framework.scale_factor(scale_factor);
             }
 
             // Resize the window
-            if let Some(size) = input.window_resized() {
+            let resized = input.window_resized();
+            if let Some(size) = resized {
                 if let Err(err) = pixels.resize_surface(size.width, size.height) {
                     log::error!("{err}");
                     *control_flow = ControlFlow::Exit;
                 }
-                framework.resize(size.width, size.height);
+                if let Some(framework) = &mut framework {
+                    framework.resize(size.width, size.height);
+                }
+            }
+
+            let gui_repaint_due = gui_repaint_at.is_some_and(|t| t <= Instant::now());
+            if redraw_needed.swap(false, Ordering::Relaxed)
+                || gui_changed
+                || gui_repaint_due
+                || resized.is_some()
+            {
+                gui_changed = false;
+                gui_repaint_at = None;
+                window.request_redraw();
+            }
+
+            if redraws_counted_since.elapsed() >= Duration::from_secs(1) {
+                log::debug!(target: LOG_TARGET_RENDERING, "{redraws} redraws/s");
+                redraws = 0;
+                redraws_counted_since = Instant::now();
             }
 
-            window.request_redraw();
+            // wake up regularly to check if the interpreter needs a redraw
+            if *control_flow != ControlFlow::Exit {
+                *control_flow = ControlFlow::WaitUntil(Instant::now() + REDRAW_POLL_INTERVAL);
+            }
         }
 
         // Draw the current frame
         match event {
             Event::RedrawRequested(_) => {
-                // send instructions executed since the last update to the debugger
-                for instruction in instructions_receiver.try_iter() {
-                    debug_gui.instruction_history.push(instruction);
+                if let (Some(framework), Some(debug_gui)) = (&mut framework, &mut debug_gui) {
+                    if save_session_receiver.try_recv().is_ok() {
+                        let chip8 = chip8.lock().unwrap();
+                        let session = Session {
+                            version: session::SESSION_VERSION,
+                            rom_file: args.rom_file.clone(),
+                            state: chip8.snapshot(),
+                            quirks: chip8.quirks,
+                            breakpoints: chip8.breakpoints().collect(),
+                            layout: debug_gui.layout.clone(),
+                        };
+                        drop(chip8);
+
+                        let p = format!("session_{}.json", file_timestamp());
+                        match session.save(&p) {
+                            Ok(()) => log::info!(target: chip8::LOG_TARGET_DEBUGGER, "Saved session to {p}"),
+                            Err(e) => log::error!("Failed to save session: {e}"),
+                        }
+                    }
+
+                    // send instructions executed since the last update to the debugger
+                    if rom_reloaded_receiver.try_recv().is_ok() {
+                        debug_gui.rom_reloaded_at = Some(Instant::now());
+                        debug_gui.instruction_history.clear();
+                    }
+                    for instruction in instructions_receiver.try_iter() {
+                        debug_gui.record_instruction(instruction);
+                    }
+                    if let Some(performance) = performance_receiver.try_iter().last() {
+                        debug_gui.performance = performance;
+                    }
+                    let c = chip8.lock().unwrap();
+
+                    // sync chip8 state to the debugger
+                    debug_gui.chip8_mode = c.mode;
+                    debug_gui.waiting_for_key = c.waiting_for_key;
+                    debug_gui.update_registers(c.registers, c.pc, c.address_register);
+                    debug_gui.breakpoints = c.breakpoints().collect();
+                    debug_gui.quirks = c.quirks;
+                    debug_gui.keys = c.keys();
+                    debug_gui.record_call_stats(c.call_stats());
+                    if debug_gui.rom_info.as_ref() != c.rom_info() {
+                        debug_gui.rom_info = c.rom_info().cloned();
+                    }
+                    debug_gui.memory.clear();
+                    debug_gui.memory.extend_from_slice(&c.memory);
+                    debug_gui.written_memory.clear();
+                    debug_gui.written_memory.extend_from_slice(c.written_memory());
+                    if debug_gui.layout.show_preview {
+                        render_vram(&c.vram, &mut debug_gui.preview, 1, DisplayOverlay::None, RenderFilter::Nearest);
+                        debug_gui.vram.copy_from_slice(&c.vram);
+                    }
+                    if let (Some(view), Some(compare)) = (&mut debug_gui.compare, &compare) {
+                        if debug_gui.layout.show_compare {
+                            let compare = compare.lock().unwrap();
+                            view.cycles = compare.cycles;
+                            view.divergence.clone_from(&compare.divergence);
+                            render_vram(&c.vram, &mut view.screens[0], 1, DisplayOverlay::None, RenderFilter::Nearest);
+                            render_vram(&compare.chip8.vram, &mut view.screens[1], 1, DisplayOverlay::None, RenderFilter::Nearest);
+                        }
+                    }
+                    if let Some(changes) = step_changes_receiver.try_iter().last() {
+                        debug_gui.step_changes = Some(changes);
+                    }
+                    if c.mode == Mode::Running {
+                        debug_gui.step_changes = None;
+                    }
+                    drop(c);
+
+                    let repaint_after = framework.prepare(&window, debug_gui);
+
+                    if debug_gui.virtual_keys != virtual_keys {
+                        chip8.lock().unwrap().queue_key_changes(
+                            physical_keys | virtual_keys,
+                            physical_keys | debug_gui.virtual_keys,
+                        );
+                        log::trace!(target: LOG_TARGET_WINIT_INPUT, "keypad: {:016b}", debug_gui.virtual_keys);
+                        virtual_keys = debug_gui.virtual_keys;
+                    }
+                    gui_repaint_at = Instant::now().checked_add(repaint_after);
                 }
-                let chip8 = chip8.lock().unwrap();
 
-                // sync chip8 state to the debugger
-                debug_gui.chip8_mode = chip8.mode;
-                debug_gui.registers = chip8.registers;
-                debug_gui.pc = chip8.pc;
-                debug_gui.address_register = chip8.address_register;
-                drop(chip8);
+                redraws += 1;
 
-                framework.prepare(&window, &mut debug_gui);
+                let title = {
+                    let chip8 = chip8.lock().unwrap();
+                    window_title(rom_name.as_deref(), chip8.mode, chip8.waiting_for_key)
+                };
+                if title != current_title {
+                    window.set_title(&title);
+                    current_title = title;
+                }
 
                 log::trace!(target: LOG_TARGET_RENDERING, "Rendering window");
 
                 let f = framebuffer.lock().unwrap();
-                pixels.frame_mut().copy_from_slice(&*f);
+                pixels.frame_mut().copy_from_slice(&f);
                 drop(f);
                 // Render everything together
                 pixels
@@ -373,7 +1236,9 @@ fn main() -> anyhow::Result<()> {
                         context.scaling_renderer.render(encoder, render_target);
 
                         // Render egui
-                        framework.render(encoder, render_target, context);
+                        if let Some(framework) = &mut framework {
+                            framework.render(encoder, render_target, context);
+                        }
 
                         Ok(())
                     })
@@ -383,15 +1248,155 @@ fn main() -> anyhow::Result<()> {
                 window_id: _,
                 event,
             } => {
-                framework.handle_event(&event);
+                if let WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            virtual_keycode: Some(key),
+                            state,
+                            ..
+                        },
+                    ..
+                } = event
+                {
+                    if let Some(i) = KEY_BINDINGS.iter().position(|k| *k == key) {
+                        let down = state == ElementState::Pressed;
+                        let pressed = if down {
+                            physical_keys | (1 << i)
+                        } else {
+                            physical_keys & !(1 << i)
+                        };
+                        chip8
+                            .lock()
+                            .unwrap()
+                            .queue_key_changes(physical_keys | virtual_keys, pressed | virtual_keys);
+                        physical_keys = pressed;
+
+                        log::trace!(target: LOG_TARGET_WINIT_INPUT, "key {}: 0x{i:X}", if down { "down" } else { "up" });
+                    }
+                }
+
+                if let Some(framework) = &mut framework {
+                    gui_changed |= framework.handle_event(&event);
+                }
             }
             _ => {}
         }
     });
 }
 
-/// Check if there is a ROM embedded in the executable and load it into CHIP8 memory
-fn load_embedded_rom(chip8: &mut Chip8) -> anyhow::Result<()> {
+/// Interpreter state and a readable version of the screen
+#[derive(Serialize)]
+struct StateDump {
+    #[serde(flatten)]
+    state: Snapshot,
+    screen: Vec<String>,
+}
+
+/// Write the interpreter state into a JSON file in the current working directory.
+/// Returns the file name
+fn dump_state(chip8: &Chip8) -> anyhow::Result<String> {
+    let p = format!("state_dump_{}.json", file_timestamp());
+
+    let dump = StateDump {
+        state: chip8.snapshot(),
+        screen: chip8::vram_rows(&chip8.vram),
+    };
+    fs::write(&p, serde_json::to_string_pretty(&dump)?)?;
+
+    Ok(p)
+}
+
+/// Wait until `deadline`, sleeping for most of the time and yielding for the last [`SPIN_DURATION`]
+/// to hit the deadline more precisely than a single sleep would
+fn wait_until(deadline: Instant) {
+    if let Some(time_left) = deadline.checked_duration_since(Instant::now()) {
+        if let Some(sleep_time) = time_left.checked_sub(SPIN_DURATION) {
+            std::thread::sleep(sleep_time);
+        }
+    }
+
+    while Instant::now() < deadline {
+        std::thread::yield_now();
+    }
+}
+
+/// Window title with the ROM name and the interpreter state, e.g. "CHIP8 — pong.ch8 [PAUSED]"
+fn window_title(rom_name: Option<&str>, mode: Mode, waiting_for_key: Option<usize>) -> String {
+    let mut title = String::from("CHIP8");
+
+    if let Some(rom_name) = rom_name {
+        title.push_str(" — ");
+        title.push_str(rom_name);
+    }
+
+    match mode {
+        Mode::Running => {}
+        Mode::Paused => title.push_str(" [PAUSED]"),
+        Mode::Halted => title.push_str(" [HALTED]"),
+    }
+
+    if waiting_for_key.is_some() {
+        title.push_str(" [WAITING FOR KEY]");
+    }
+
+    title
+}
+
+/// The key that quits the interpreter, if any
+#[derive(Debug, Clone, Copy)]
+struct QuitKey(Option<VirtualKeyCode>);
+
+impl FromStr for QuitKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const FUNCTION_KEYS: [VirtualKeyCode; 12] = [
+            VirtualKeyCode::F1,
+            VirtualKeyCode::F2,
+            VirtualKeyCode::F3,
+            VirtualKeyCode::F4,
+            VirtualKeyCode::F5,
+            VirtualKeyCode::F6,
+            VirtualKeyCode::F7,
+            VirtualKeyCode::F8,
+            VirtualKeyCode::F9,
+            VirtualKeyCode::F10,
+            VirtualKeyCode::F11,
+            VirtualKeyCode::F12,
+        ];
+
+        let s = s.to_lowercase();
+        let key = match s.as_str() {
+            "none" => None,
+            "escape" => Some(VirtualKeyCode::Escape),
+            "backspace" => Some(VirtualKeyCode::Back),
+            "delete" => Some(VirtualKeyCode::Delete),
+            "end" => Some(VirtualKeyCode::End),
+            "pause" => Some(VirtualKeyCode::Pause),
+            _ => {
+                let function_key = s
+                    .strip_prefix('f')
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .and_then(|n| FUNCTION_KEYS.get(n.checked_sub(1)?));
+
+                match function_key {
+                    Some(key) => Some(*key),
+                    None => anyhow::bail!("unsupported quit key {s}"),
+                }
+            }
+        };
+
+        Ok(QuitKey(key))
+    }
+}
+
+/// Current time, formatted to be safely usable in file names
+fn file_timestamp() -> String {
+    Utc::now().format("%Y-%m-%dT%H-%M-%S").to_string()
+}
+
+/// Read the ROM embedded in the executable, returning Err when there is none
+fn embedded_rom() -> anyhow::Result<Vec<u8>> {
     let exe_path = std::env::current_exe()?;
 
     let mut exe = File::open(exe_path)?;
@@ -419,10 +1424,7 @@ fn load_embedded_rom(chip8: &mut Chip8) -> anyhow::Result<()> {
 
     log::info!("Loading rom from {rom_start:X}");
 
-    chip8.memory[chip8::PC_INIT..(rom_len as usize + chip8::PC_INIT)]
-        .copy_from_slice(&exe_file[rom_start..(rom_len as usize + rom_start)]);
-
-    Ok(())
+    Ok(exe_file[rom_start..(rom_len + rom_start)].to_vec())
 }
 
 /// checks for the embedded rom trailer and reads the length, returning Err when there is no trailer
@@ -441,27 +1443,43 @@ fn get_embedded_rom_length(exe: &mut File) -> anyhow::Result<usize> {
     Ok(rom_len.into())
 }
 
-/// Render the CHIP8 vram to the Pixels framebuffer
-fn render_vram(vram: &[u8], frame: &mut [u8]) {
-    const ALPHA: u8 = 0xFF;
-    const ON: [u8; 4] = [0x66, 0x66, 0x99, ALPHA];
-    const OFF: [u8; 4] = [0x29, 0x29, 0x3d, ALPHA];
-
+/// Render the CHIP8 vram into an RGBA buffer, every vram pixel becomes `scale` x `scale` pixels.
+/// Used for the Pixels framebuffer and the 1:1 preview in the debugger
+fn render_vram(
+    vram: &[u8],
+    frame: &mut [u8],
+    scale: u32,
+    overlay: DisplayOverlay,
+    filter: RenderFilter,
+) {
     for vram_y in 0..chip8::DISPLAY_HEIGHT {
         for vram_x in 0..chip8::DISPLAY_WIDTH {
-            let color = if vram[chip8::vram_index(vram_x, vram_y).unwrap()] == 1 {
-                OFF
-            } else {
-                ON
-            };
+            let color = pixel_color(vram, vram_x, vram_y);
 
             // every vram pixel is scaled up
-            for x in 0..DISPLAY_WINDOW_SCALE {
-                for y in 0..DISPLAY_WINDOW_SCALE {
-                    let frame_x = u32::from(vram_x) * DISPLAY_WINDOW_SCALE + x;
-                    let frame_y = u32::from(vram_y) * DISPLAY_WINDOW_SCALE + y;
-
-                    let i = (frame_x as usize + WINDOW_WIDTH as usize * frame_y as usize) * 4;
+            for x in 0..scale {
+                for y in 0..scale {
+                    let frame_x = u32::from(vram_x) * scale + x;
+                    let frame_y = u32::from(vram_y) * scale + y;
+
+                    let color = match filter {
+                        RenderFilter::Nearest => color,
+                        RenderFilter::Crt => crt_color(vram, (vram_x, vram_y), (x, y), scale),
+                    };
+
+                    let on_overlay_line = match overlay {
+                        DisplayOverlay::None => false,
+                        DisplayOverlay::Grid => x == 0 || y == 0,
+                        DisplayOverlay::Scanlines => frame_y % 2 == 1,
+                    };
+                    let color = if on_overlay_line {
+                        darken(color)
+                    } else {
+                        color
+                    };
+
+                    let frame_width = u32::from(chip8::DISPLAY_WIDTH) * scale;
+                    let i = (frame_x as usize + frame_width as usize * frame_y as usize) * 4;
                     frame[i] = color[0];
                     frame[i + 1] = color[1];
                     frame[i + 2] = color[2];
@@ -471,3 +1489,137 @@ fn render_vram(vram: &[u8], frame: &mut [u8]) {
         }
     }
 }
+
+fn pixel_color(vram: &[u8], vram_x: u16, vram_y: u16) -> [u8; 4] {
+    const ALPHA: u8 = 0xFF;
+    const ON: [u8; 4] = [0x66, 0x66, 0x99, ALPHA];
+    const OFF: [u8; 4] = [0x29, 0x29, 0x3d, ALPHA];
+
+    if vram[chip8::vram_index(vram_x, vram_y).unwrap()] == 1 {
+        OFF
+    } else {
+        ON
+    }
+}
+
+/// Color of the point `pos` inside of the scaled up vram pixel at `vram_pos` for
+/// [`RenderFilter::Crt`]: the edges are blended with the neighbouring vram pixels and every other
+/// line is a little darker
+fn crt_color(vram: &[u8], vram_pos: (u16, u16), pos: (u32, u32), scale: u32) -> [u8; 4] {
+    let (vram_x, vram_y) = vram_pos;
+    let (x, y) = pos;
+    let mut color = pixel_color(vram, vram_x, vram_y);
+
+    // the neighbour on the side of the edge the point is on, if there is one
+    let neighbour_x = match x {
+        0 => vram_x.checked_sub(1),
+        x if x == scale - 1 => Some(vram_x + 1).filter(|x| *x < chip8::DISPLAY_WIDTH),
+        _ => None,
+    };
+    let neighbour_y = match y {
+        0 => vram_y.checked_sub(1),
+        y if y == scale - 1 => Some(vram_y + 1).filter(|y| *y < chip8::DISPLAY_HEIGHT),
+        _ => None,
+    };
+    if let Some(neighbour_x) = neighbour_x {
+        color = blend(color, pixel_color(vram, neighbour_x, vram_y));
+    }
+    if let Some(neighbour_y) = neighbour_y {
+        color = blend(color, pixel_color(vram, vram_x, neighbour_y));
+    }
+
+    if y % 2 == 1 {
+        let [r, g, b, a] = color;
+        color = [r - r / 8, g - g / 8, b - b / 8, a];
+    }
+
+    color
+}
+
+/// Mix two colors equally, keeping the alpha of the first one
+fn blend(a: [u8; 4], b: [u8; 4]) -> [u8; 4] {
+    let mix = |i: usize| a[i] / 2 + b[i] / 2;
+    [mix(0), mix(1), mix(2), a[3]]
+}
+
+/// Blend a color with black, used to draw overlay lines that stay faint on both pixel colors
+fn darken(color: [u8; 4]) -> [u8; 4] {
+    let [r, g, b, a] = color;
+    [r / 4 * 3, g / 4 * 3, b / 4 * 3, a]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The color of the window pixel at `x`, `y` in a frame rendered by [`render_vram`]
+    fn frame_pixel(frame: &[u8], x: u32, y: u32) -> [u8; 4] {
+        let i = (x + WINDOW_WIDTH * y) as usize * 4;
+        frame[i..i + 4].try_into().unwrap()
+    }
+
+    /// A frame rendered with an overlay has the size of the window, and the overlay darkens
+    /// exactly the grid or scanline pixels of both pixel colors
+    #[test]
+    fn overlay_frame() {
+        let mut vram =
+            vec![0; usize::from(chip8::DISPLAY_WIDTH) * usize::from(chip8::DISPLAY_HEIGHT)];
+        vram[chip8::vram_index(1, 0).unwrap()] = 1;
+        let on = pixel_color(&vram, 1, 0);
+        let off = pixel_color(&vram, 0, 0);
+
+        for (overlay, checks) in [
+            (
+                DisplayOverlay::None,
+                [
+                    (15, 5, on),
+                    (10, 5, on),
+                    (5, 5, off),
+                    (0, 5, off),
+                    (15, 1, on),
+                ],
+            ),
+            (
+                DisplayOverlay::Grid,
+                [
+                    (15, 5, on),
+                    (10, 5, darken(on)),
+                    (15, 0, darken(on)),
+                    (5, 5, off),
+                    (0, 5, darken(off)),
+                ],
+            ),
+            (
+                DisplayOverlay::Scanlines,
+                [
+                    (15, 4, on),
+                    (15, 5, darken(on)),
+                    (10, 4, on),
+                    (5, 4, off),
+                    (5, 1, darken(off)),
+                ],
+            ),
+        ] {
+            let mut frame = vec![0; (WINDOW_WIDTH * WINDOW_HEIGHT * 4) as usize];
+            render_vram(
+                &vram,
+                &mut frame,
+                DISPLAY_WINDOW_SCALE,
+                overlay,
+                RenderFilter::Nearest,
+            );
+            assert!(
+                frame.chunks(4).all(|pixel| pixel[3] == 0xFF),
+                "Every one of the {WINDOW_WIDTH}x{WINDOW_HEIGHT} window pixels should be rendered with the {overlay} overlay"
+            );
+
+            for (x, y, expected) in checks {
+                assert_eq!(
+                    frame_pixel(&frame, x, y),
+                    expected,
+                    "Pixel {x},{y} with the {overlay} overlay"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use crate::chip8::{instructions::supported_opcodes, platform::Platform, rom_info::RomInfo};
+
+/// Print what can be told about a ROM without running it: size, checksum, the detected platform,
+/// how often each opcode appears and which features it seems to use.
+///
+/// The ROM is scanned as a sequence of two byte words, so sprites and other data are counted as
+/// instructions, too. Treat the numbers as hints
+pub fn run(rom_file: &str) -> anyhow::Result<()> {
+    let rom = std::fs::read(rom_file)?;
+    let mut info = RomInfo::new(&rom);
+    info.name = Path::new(rom_file)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned());
+
+    println!("{info}");
+    println!("Detected platform: {}", Platform::detect(&rom));
+
+    let opcodes: Vec<u16> = rom
+        .chunks_exact(2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .collect();
+    let mut counts = vec![0; supported_opcodes().len()];
+    for opcode in &opcodes {
+        if let Some(i) = supported_opcodes()
+            .iter()
+            .position(|info| info.matches(*opcode))
+        {
+            counts[i] += 1;
+        }
+    }
+    let count = |pattern: &str| {
+        supported_opcodes()
+            .iter()
+            .zip(&counts)
+            .filter(|(info, _)| info.pattern == pattern)
+            .map(|(_, n)| n)
+            .sum::<usize>()
+    };
+
+    println!();
+    println!("Opcodes ({} words):", opcodes.len());
+    for (info, n) in supported_opcodes().iter().zip(&counts) {
+        if *n > 0 {
+            println!("  {:<4}  {:<4}  {n:>5}", info.pattern, info.mnemonic);
+        }
+    }
+    let unknown = opcodes.len() - counts.iter().sum::<usize>();
+    println!("  unknown     {unknown:>5}");
+
+    println!();
+    println!("Draws: {}", yes_no(count("DXYN") + count("00E0")));
+    println!(
+        "Reads keys: {}",
+        yes_no(count("EX9E") + count("EXA1") + count("FX0A"))
+    );
+    println!("Sound: {}", yes_no(count("FX18")));
+
+    Ok(())
+}
+
+fn yes_no(count: usize) -> String {
+    if count > 0 {
+        format!("yes ({count} words)")
+    } else {
+        "no".to_owned()
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    path::Path,
+    sync::mpsc::{self, RecvTimeoutError},
+    time::Duration,
+};
+
+use notify::{RecursiveMode, Watcher};
+
+pub const LOG_TARGET_WATCH: &str = "WATCH";
+
+/// How long the ROM file has to stay untouched before it is reloaded.
+/// Assemblers and editors tend to write a file in several steps, this avoids reloading half written ROMs
+const DEBOUNCE_DURATION: Duration = Duration::from_millis(250);
+
+/// Watch the ROM file for changes and call `on_change` with the new content after it has been modified.
+///
+/// The parent directory is watched instead of the file itself, so we keep getting events when
+/// the file is replaced or temporarily missing while being saved.
+pub fn watch_rom(
+    rom_file: impl AsRef<Path>,
+    on_change: impl Fn(Vec<u8>) + Send + 'static,
+) -> anyhow::Result<()> {
+    let rom_file = rom_file.as_ref().canonicalize()?;
+    let directory = rom_file
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("ROM file has no parent directory"))?
+        .to_path_buf();
+
+    let (event_sender, event_receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(event_sender)?;
+    watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+
+    log::info!(target: LOG_TARGET_WATCH, "Watching {} for changes", rom_file.display());
+
+    std::thread::spawn(move || {
+        // the watcher stops as soon as it is dropped
+        let _watcher = watcher;
+
+        let is_rom_event = |event: &notify::Result<notify::Event>| match event {
+            // only the parent directory is watched, so comparing the file name is enough
+            Ok(event) => event
+                .paths
+                .iter()
+                .any(|p| p.file_name() == rom_file.file_name()),
+            Err(e) => {
+                log::warn!(target: LOG_TARGET_WATCH, "{e}");
+                false
+            }
+        };
+
+        while let Ok(event) = event_receiver.recv() {
+            if !is_rom_event(&event) {
+                continue;
+            }
+
+            // wait until the file did not change for a while
+            loop {
+                match event_receiver.recv_timeout(DEBOUNCE_DURATION) {
+                    Ok(_) => {}
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+
+            match std::fs::read(&rom_file) {
+                Ok(rom) => {
+                    log::info!(target: LOG_TARGET_WATCH, "{} changed, reloading", rom_file.display());
+                    on_change(rom);
+                }
+                // the file might be missing for a moment while it is being saved,
+                // we will get another event once it is back
+                Err(e) => {
+                    log::debug!(target: LOG_TARGET_WATCH, "Can't read {}: {e}", rom_file.display());
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
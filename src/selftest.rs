@@ -0,0 +1,381 @@
+use std::time::Instant;
+
+use crate::chip8::{
+    self,
+    instructions::{assemble, Instruction},
+    Chip8,
+};
+
+/// How often the self test ROM is executed to get a meaningful timing
+const SELFTEST_RUNS: u32 = 1000;
+
+/// Upper bound of executed instructions per run, in case the ROM never reaches its final loop
+const SELFTEST_MAX_CYCLES: usize = 1000;
+
+/// Key that is held down during the self test, used to satisfy `SkipIfKey` and `WaitForKey`
+const SELFTEST_KEY: u8 = 0xA;
+
+/// Register set to [`FAIL_MARKER`] by instructions that should have been skipped
+const FAIL_REGISTER: u8 = 0xE;
+const FAIL_MARKER: u8 = 0xEE;
+
+/// Where the self test ROM stores and loads data
+const DATA_ADDRESS: u16 = 0x400;
+
+/// Register contents after the self test ROM finished
+const EXPECTED_REGISTERS: [u8; 16] = [1, 2, 3, 123, 4, 8, 1, 0, 5, 0xA, 0x20, 0x20, 0, 3, 0, 0];
+
+/// Generate a ROM that executes every implemented instruction, run it without a window
+/// and check the final machine state
+pub fn run() -> anyhow::Result<()> {
+    let rom = generate_rom();
+    log::info!("Generated self test ROM with {} bytes", rom.len());
+
+    let mut cycles = 0;
+    let start = Instant::now();
+
+    for _ in 0..SELFTEST_RUNS {
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&rom)?;
+
+        cycles += run_until_final_loop(&mut chip8)?;
+        check_final_state(&chip8, DATA_ADDRESS + 3)?;
+    }
+
+    let elapsed = start.elapsed();
+
+    // FX55/FX65 leave I unchanged without the increment quirk
+    let mut chip8 = Chip8::new();
+    chip8.quirks.increment_i_on_store = false;
+    chip8.load_rom_bytes(&rom)?;
+    run_until_final_loop(&mut chip8)?;
+    check_final_state(&chip8, DATA_ADDRESS)?;
+
+    log::info!(
+        "Self test passed: {SELFTEST_RUNS} runs, {cycles} instructions in {elapsed:?} ({:.0} instructions/s)",
+        f64::from(cycles) / elapsed.as_secs_f64()
+    );
+
+    Ok(())
+}
+
+/// Build the self test program, using the instruction encoder
+pub fn generate_rom() -> Vec<u8> {
+    let mut program: Vec<Instruction> = Vec::new();
+
+    // address of the instruction that will be pushed next
+    let next_address =
+        |program: &Vec<Instruction>| u16::try_from(chip8::PC_INIT + program.len() * 2).unwrap();
+    let fail = Instruction::StoreNumberInRegister {
+        number: FAIL_MARKER,
+        register: FAIL_REGISTER,
+    };
+
+    // arithmetic
+    program.extend([
+        Instruction::Clear,
+        Instruction::StoreNumberInRegister {
+            number: 0x05,
+            register: 0x0,
+        },
+        Instruction::StoreNumberInRegister {
+            number: 0x03,
+            register: 0x1,
+        },
+        Instruction::StoreNumberInRegister {
+            number: 0x08,
+            register: 0x5,
+        },
+        Instruction::AddToRegister {
+            register: 0x0,
+            value: 1,
+        },
+        Instruction::CopyRegister {
+            register_x: 0x2,
+            register_y: 0x0,
+        },
+        Instruction::OrRegisters {
+            register_x: 0x2,
+            register_y: 0x1,
+        },
+        Instruction::AndRegisters {
+            register_x: 0x2,
+            register_y: 0x0,
+        },
+        Instruction::XorRegisters {
+            register_x: 0x2,
+            register_y: 0x1,
+        },
+        Instruction::AddRegisters {
+            register_x: 0x2,
+            register_y: 0x0,
+        },
+        Instruction::SubRegisters {
+            register_x: 0x2,
+            register_y: 0x1,
+        },
+        Instruction::SubRegistersOtherWayArround {
+            register_x: 0x1,
+            register_y: 0x2,
+        },
+        Instruction::LeftShiftRegister {
+            register_x: 0x3,
+            register_y: 0x2,
+        },
+        Instruction::RightShiftRegister {
+            register_x: 0x4,
+            register_y: 0x2,
+        },
+    ]);
+
+    // conditional skips, each one has to skip the fail marker
+    program.extend([
+        Instruction::SkipIfRegisterEqTo {
+            register: 0x0,
+            value: 6,
+        },
+        fail,
+        Instruction::SkipIfRegisterNeqTo {
+            register: 0x0,
+            value: 7,
+        },
+        fail,
+        Instruction::SkipIfRegistersEq {
+            register_x: 0x2,
+            register_y: 0x5,
+        },
+        fail,
+        Instruction::SkipIfRegistersNeq {
+            register_x: 0x0,
+            register_y: 0x1,
+        },
+        fail,
+    ]);
+
+    // jumps and subroutines
+    program.push(Instruction::JumpToAddress {
+        address: next_address(&program) + 4,
+    });
+    program.push(fail);
+
+    let subroutine_call = program.len();
+    program.push(Instruction::ExecuteSubroutine { address: 0 });
+
+    program.push(Instruction::StoreNumberInRegister {
+        number: 2,
+        register: 0x0,
+    });
+    program.push(Instruction::JumpOffsetV0 {
+        address: next_address(&program) + 2,
+    });
+    program.push(fail);
+
+    // random numbers, input and timers
+    program.extend([
+        Instruction::StoreNumberInRegister {
+            number: 0xFF,
+            register: 0x7,
+        },
+        Instruction::RandomNumber {
+            register_x: 0x7,
+            mask: 0,
+        },
+        Instruction::StoreNumberInRegister {
+            number: SELFTEST_KEY,
+            register: 0x8,
+        },
+        Instruction::SkipIfKey { register_x: 0x8 },
+        fail,
+        // keys > 0xF don't exist and are never down
+        Instruction::StoreNumberInRegister {
+            number: 0xFF,
+            register: 0x8,
+        },
+        Instruction::SkipIfNotKey { register_x: 0x8 },
+        fail,
+        Instruction::StoreNumberInRegister {
+            number: 0x05,
+            register: 0x8,
+        },
+        Instruction::SkipIfNotKey { register_x: 0x8 },
+        fail,
+        Instruction::WaitForKey { register_x: 0x9 },
+        Instruction::StoreNumberInRegister {
+            number: 0x20,
+            register: 0xA,
+        },
+        Instruction::SetDelayTimer { register_x: 0xA },
+        Instruction::ReadDelayTimer { register_x: 0xB },
+        Instruction::SetSoundTimer { register_x: 0xA },
+    ]);
+
+    // drawing
+    program.extend([
+        Instruction::LoadFontCharacter { register_x: 0x0 },
+        Instruction::StoreNumberInRegister {
+            number: 0,
+            register: 0xC,
+        },
+        Instruction::StoreNumberInRegister {
+            number: 0,
+            register: 0xD,
+        },
+        Instruction::DrawSprite {
+            register_x: 0xC,
+            register_y: 0xD,
+            len: 5,
+        },
+    ]);
+
+    // memory
+    program.extend([
+        Instruction::StoreNumberInRegister {
+            number: 123,
+            register: 0x3,
+        },
+        Instruction::SetAddressRegister {
+            address: DATA_ADDRESS,
+        },
+        Instruction::BinaryCodedDecimal { register_x: 0x3 },
+        Instruction::StoreNumberInRegister {
+            number: 3,
+            register: 0xD,
+        },
+        Instruction::AddXtoI { register_x: 0xD },
+        Instruction::StoreRegisters { register_x: 0x2 },
+        Instruction::SetAddressRegister {
+            address: DATA_ADDRESS,
+        },
+        Instruction::LoadRegisters { register_x: 0x2 },
+    ]);
+
+    // the final loop
+    program.push(Instruction::JumpToAddress {
+        address: next_address(&program),
+    });
+
+    program[subroutine_call] = Instruction::ExecuteSubroutine {
+        address: next_address(&program),
+    };
+    program.extend([
+        Instruction::AddToRegister {
+            register: 0x6,
+            value: 1,
+        },
+        Instruction::Return,
+    ]);
+
+    assemble(program)
+}
+
+/// Execute the loaded ROM until it jumps to itself.
+/// Returns the number of executed instructions
+fn run_until_final_loop(chip8: &mut Chip8) -> anyhow::Result<u32> {
+    chip8.set_keys(1 << SELFTEST_KEY);
+
+    for cycle in 1..=SELFTEST_MAX_CYCLES {
+        let pc = chip8.pc;
+        let next_instruction = chip8.peek_next()?;
+        anyhow::ensure!(chip8.pc == pc, "peek_next changed the PC");
+
+        let instruction = chip8.step_cycle()?.instruction;
+        anyhow::ensure!(
+            instruction == next_instruction,
+            "peek_next returned {next_instruction:?}, but {instruction:?} was executed"
+        );
+
+        // behave like the frontend, which resolves the wait once the key is released
+        if let Some(register) = chip8.waiting_for_key.take() {
+            chip8.registers[register] = SELFTEST_KEY;
+        }
+
+        if let Instruction::JumpToAddress { address } = instruction {
+            if usize::from(address) == pc {
+                return Ok(u32::try_from(cycle)?);
+            }
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "Self test ROM did not finish within {SELFTEST_MAX_CYCLES} instructions"
+    ))
+}
+
+fn check_final_state(chip8: &Chip8, expected_address_register: u16) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        chip8.registers[usize::from(FAIL_REGISTER)] != FAIL_MARKER,
+        "An instruction that should have been skipped was executed"
+    );
+    anyhow::ensure!(
+        chip8.registers == EXPECTED_REGISTERS,
+        "Unexpected registers {:X?}, expected {EXPECTED_REGISTERS:X?}",
+        chip8.registers
+    );
+    anyhow::ensure!(
+        chip8.address_register == expected_address_register,
+        "Unexpected I: {:X}, expected {expected_address_register:X}",
+        chip8.address_register
+    );
+
+    let data = usize::from(DATA_ADDRESS);
+    anyhow::ensure!(
+        chip8.memory[data..data + 6] == [1, 2, 3, 2, 5, 8],
+        "Unexpected memory content {:X?}",
+        &chip8.memory[data..data + 6]
+    );
+    anyhow::ensure!(chip8.delay_timer == 0x20, "Unexpected delay timer");
+    anyhow::ensure!(chip8.sound_timer == 0x20, "Unexpected sound timer");
+
+    // the font character "2" has 14 pixels set
+    expect("Pixels drawn", chip8.pixel_count(), 14)?;
+
+    Ok(())
+}
+
+fn expect<T: PartialEq + Copy + std::fmt::Debug>(
+    what: &str,
+    actual: T,
+    expected: T,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        actual == expected,
+        "{what}: expected {expected:?}, got {actual:?}"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::platform::Platform;
+
+    #[test]
+    fn final_state() {
+        let rom = generate_rom();
+        let mut chip8 = Chip8::new();
+        chip8.load_rom_bytes(&rom).unwrap();
+        run_until_final_loop(&mut chip8).unwrap();
+        check_final_state(&chip8, DATA_ADDRESS + 3).unwrap();
+
+        // the final loop jumps to itself without changing anything
+        let hash = chip8.state_hash();
+        chip8.step_cycle().unwrap();
+        assert_eq!(chip8.state_hash(), hash);
+    }
+
+    /// FX55/FX65 leave I unchanged without the increment quirk
+    #[test]
+    fn final_state_keeping_address_register() {
+        let mut chip8 = Chip8::new();
+        chip8.quirks.increment_i_on_store = false;
+        chip8.load_rom_bytes(&generate_rom()).unwrap();
+        run_until_final_loop(&mut chip8).unwrap();
+        check_final_state(&chip8, DATA_ADDRESS).unwrap();
+    }
+
+    /// The ROM sticks to CHIP-8 instructions, so it runs everywhere
+    #[test]
+    fn platform() {
+        assert_eq!(Platform::detect(&generate_rom()), Platform::Chip8);
+    }
+}
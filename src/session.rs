@@ -0,0 +1,49 @@
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chip8::{breakpoint::Condition, quirks::Quirks, snapshot::Snapshot},
+    debug_gui::DebuggerLayout,
+};
+
+/// Increased whenever the session format changes in an incompatible way
+pub const SESSION_VERSION: u32 = 1;
+
+/// Everything needed to continue debugging where a previous run left off
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub version: u32,
+    pub rom_file: Option<String>,
+    pub state: Snapshot,
+    #[serde(default)]
+    pub quirks: Quirks,
+    pub breakpoints: Vec<Condition>,
+    pub layout: DebuggerLayout,
+}
+
+/// Only the version of a session, to check it before parsing the rest of the file
+#[derive(Deserialize)]
+struct SessionVersion {
+    version: u32,
+}
+
+impl Session {
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let content = fs::read_to_string(path)?;
+
+        let SessionVersion { version } = serde_json::from_str(&content)?;
+        anyhow::ensure!(
+            version == SESSION_VERSION,
+            "Session was saved in format version {version}, but only version {SESSION_VERSION} is supported"
+        );
+
+        Ok(serde_json::from_str(&content)?)
+    }
+}
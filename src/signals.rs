@@ -0,0 +1,41 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set by SIGUSR1, see [`take_toggle_pause`]
+static TOGGLE_PAUSE: AtomicBool = AtomicBool::new(false);
+/// Set by SIGUSR2, see [`take_dump_state`]
+static DUMP_STATE: AtomicBool = AtomicBool::new(false);
+
+/// Only sets a flag, anything else is not safe to do in a signal handler
+extern "C" fn handle_signal(signal: libc::c_int) {
+    match signal {
+        libc::SIGUSR1 => TOGGLE_PAUSE.store(true, Ordering::Relaxed),
+        libc::SIGUSR2 => DUMP_STATE.store(true, Ordering::Relaxed),
+        _ => {}
+    }
+}
+
+/// Handle SIGUSR1 to toggle pause and SIGUSR2 to dump the interpreter state, so scripts can
+/// control a running instance, e.g. with `kill -USR1 <pid>`
+pub fn install() -> anyhow::Result<()> {
+    for signal in [libc::SIGUSR1, libc::SIGUSR2] {
+        // SAFETY: the handler only stores into atomics, which is async-signal-safe
+        let previous =
+            unsafe { libc::signal(signal, handle_signal as *const () as libc::sighandler_t) };
+        anyhow::ensure!(
+            previous != libc::SIG_ERR,
+            "Failed to install the handler for signal {signal}"
+        );
+    }
+
+    Ok(())
+}
+
+/// Whether SIGUSR1 was received since the last call
+pub fn take_toggle_pause() -> bool {
+    TOGGLE_PAUSE.swap(false, Ordering::Relaxed)
+}
+
+/// Whether SIGUSR2 was received since the last call
+pub fn take_dump_state() -> bool {
+    DUMP_STATE.swap(false, Ordering::Relaxed)
+}
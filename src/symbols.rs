@@ -0,0 +1,50 @@
+use std::{collections::BTreeMap, path::Path};
+
+/// Names for addresses in a ROM, e.g. labels and data regions, shown by the disassembler.
+///
+/// Symbols are read from a `.sym` file next to the ROM, with one `0xADDR name` pair per line.
+/// Empty lines and lines starting with `#` are ignored.
+#[derive(Debug, Default, Clone)]
+pub struct Symbols(BTreeMap<usize, String>);
+
+impl Symbols {
+    /// Load the `.sym` file that belongs to the ROM file, e.g. `pong.sym` for `pong.ch8`.
+    /// Returns no symbols if there is no such file
+    pub fn for_rom(rom_file: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = rom_file.as_ref().with_extension("sym");
+
+        if !path.exists() {
+            return Ok(Symbols::default());
+        }
+
+        let symbols = Self::parse(&std::fs::read_to_string(&path)?)?;
+        log::info!("Loaded {} symbols from {}", symbols.0.len(), path.display());
+
+        Ok(symbols)
+    }
+
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        let mut symbols = BTreeMap::new();
+
+        for (line_number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (address, name) = line.split_once(char::is_whitespace).ok_or_else(|| {
+                anyhow::anyhow!("Line {}: expected \"0xADDR name\"", line_number + 1)
+            })?;
+            let address = usize::from_str_radix(address.trim_start_matches("0x"), 16)
+                .map_err(|e| anyhow::anyhow!("Line {}: invalid address: {e}", line_number + 1))?;
+
+            symbols.insert(address, name.trim().to_owned());
+        }
+
+        Ok(Symbols(symbols))
+    }
+
+    pub fn get(&self, address: usize) -> Option<&str> {
+        self.0.get(&address).map(String::as_str)
+    }
+}
@@ -0,0 +1,63 @@
+use std::{collections::VecDeque, fmt::Display, io::Write};
+
+use chip8stuff::chip8::{instructions::Instruction, Chip8};
+
+/// One instruction in the [`Trace`], recorded before it is executed, so the failing instruction
+/// is the last entry
+struct TraceEntry {
+    address: usize,
+    /// None if the address is outside of memory
+    opcode: Option<u16>,
+    /// None for unknown opcodes
+    instruction: Option<Instruction>,
+}
+
+impl Display for TraceEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.opcode, self.instruction) {
+            (Some(opcode), Some(instruction)) => {
+                write!(f, "{:03X}: {opcode:04X}  {instruction}", self.address)
+            }
+            (Some(opcode), None) => write!(f, "{:03X}: {opcode:04X}  unknown opcode", self.address),
+            (None, _) => write!(f, "{:03X}: ----  outside of memory", self.address),
+        }
+    }
+}
+
+/// The last executed instructions, written to stderr when an instruction fails or the
+/// interpreter panics. Gives some context for a bug report without running with `--verbose`
+pub struct Trace {
+    entries: VecDeque<TraceEntry>,
+    len: usize,
+}
+
+impl Trace {
+    pub fn new(len: usize) -> Self {
+        Trace {
+            entries: VecDeque::with_capacity(len),
+            len,
+        }
+    }
+
+    /// Remember the instruction at the PC, call it right before executing it
+    pub fn record(&mut self, chip8: &Chip8) {
+        if self.entries.len() == self.len {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(TraceEntry {
+            address: chip8.pc,
+            opcode: chip8.opcode_at(chip8.pc).ok(),
+            instruction: chip8.peek_next().ok(),
+        });
+    }
+
+    /// Write the recorded instructions to stderr, oldest first
+    pub fn dump(&self) {
+        let mut err = std::io::stderr().lock();
+        let _ = writeln!(err, "Last {} instructions:", self.entries.len());
+        for entry in &self.entries {
+            let _ = writeln!(err, "  {entry}");
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! Setup shared by the integration tests. Every test file compiles it on its own and uses only
+//! a part of it
+#![allow(dead_code)]
+
+use chip8stuff::chip8::{
+    error::EmuError,
+    instructions::{assemble, Instruction},
+    Chip8,
+};
+
+/// Where the test programs store and load data
+pub const DATA_ADDRESS: u16 = 0x400;
+
+/// A new interpreter with the program loaded, nothing executed yet
+pub fn load(program: impl IntoIterator<Item = Instruction>) -> Chip8 {
+    let mut chip8 = Chip8::new();
+    chip8.load_rom_bytes(&assemble(program)).unwrap();
+    chip8
+}
+
+/// Load the program, prepare the interpreter with `setup` and execute every instruction once
+pub fn run_program(
+    program: &[Instruction],
+    setup: impl FnOnce(&mut Chip8),
+) -> Result<Chip8, EmuError> {
+    let mut chip8 = load(program.iter().copied());
+    setup(&mut chip8);
+
+    for _ in program {
+        chip8.step_cycle()?;
+    }
+
+    Ok(chip8)
+}
+
+/// A jump to itself when placed at `address`, the usual end of a ROM
+pub fn jump_to_itself(address: usize) -> Instruction {
+    Instruction::JumpToAddress {
+        address: u16::try_from(address).unwrap(),
+    }
+}
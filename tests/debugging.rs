@@ -0,0 +1,252 @@
+mod common;
+
+use chip8stuff::chip8::{
+    self,
+    instructions::Instruction,
+    snapshot::{Change, Snapshot},
+    Chip8, Mode,
+};
+use common::{jump_to_itself, load};
+
+/// The diff of a single step lists exactly what the instruction changed
+#[test]
+fn snapshot_diff() {
+    let mut chip8 = load([Instruction::AddToRegister {
+        register: 0x3,
+        value: 0x05,
+    }]);
+
+    let before = chip8.snapshot();
+    chip8.step_cycle().unwrap();
+    let changes = before.diff(&chip8.snapshot());
+
+    let pc = chip8::PC_INIT;
+    assert_eq!(
+        changes,
+        [
+            Change::Pc {
+                before: pc,
+                after: pc + 2
+            },
+            Change::Register {
+                register: 0x3,
+                before: 0,
+                after: 0x05
+            },
+        ]
+    );
+}
+
+/// Identical states hash equally, a single changed register changes the hash
+#[test]
+fn state_hash() {
+    let program = [
+        Instruction::StoreNumberInRegister {
+            number: 0x12,
+            register: 0x7,
+        },
+        jump_to_itself(chip8::PC_INIT + 2),
+    ];
+    let mut first = load(program);
+    first.step_cycle().unwrap();
+    let mut second = load(program);
+    second.step_cycle().unwrap();
+
+    let hash = first.state_hash();
+    assert_eq!(second.state_hash(), hash, "Hash of identical states");
+
+    // the final loop jumps to itself without changing anything
+    first.step_cycle().unwrap();
+    assert_eq!(first.state_hash(), hash, "Hash after the final loop");
+
+    second.registers[0x7] ^= 1;
+    assert_ne!(second.state_hash(), hash, "Hash after changing a register");
+}
+
+/// A return before any call is reported, and fails instead of crashing when executed
+#[test]
+fn early_return() {
+    let mut chip8 = load([Instruction::Clear, Instruction::Return]);
+    assert_eq!(chip8.early_return(), Some(chip8::PC_INIT + 2));
+    chip8.step_cycle().unwrap();
+    assert!(chip8.step_cycle().is_err(), "Returning with an empty stack");
+
+    let chip8 = load([
+        Instruction::ExecuteSubroutine {
+            address: u16::try_from(chip8::PC_INIT).unwrap() + 4,
+        },
+        Instruction::Clear,
+        Instruction::Return,
+    ]);
+    assert_eq!(chip8.early_return(), None, "Return after a call");
+}
+
+/// Running to an address pauses exactly there, and the temporary breakpoint is gone afterwards
+#[test]
+fn run_to() {
+    let mut chip8 = load((0..8).map(|register| Instruction::StoreNumberInRegister {
+        number: 1,
+        register,
+    }));
+    chip8.mode = Mode::Paused;
+
+    let target = chip8::PC_INIT + 6;
+    chip8.run_to(target);
+    assert_eq!(
+        chip8.mode,
+        Mode::Running,
+        "Mode after running to an address"
+    );
+
+    while chip8.mode == Mode::Running {
+        chip8.step_cycle().unwrap();
+    }
+    assert_eq!(chip8.pc, target, "PC after running to an address");
+    assert_eq!(chip8.registers[2], 1, "Last register set before the target");
+    assert_eq!(chip8.registers[3], 0, "Register set at the target");
+    assert!(
+        chip8.breakpoints().next().is_none(),
+        "Running to an address should not leave a breakpoint behind"
+    );
+
+    // the breakpoint was removed, so continuing doesn't stop at the target again
+    chip8.mode = Mode::Running;
+    for _ in 0..4 {
+        chip8.step_cycle().unwrap();
+    }
+    assert_eq!(chip8.mode, Mode::Running, "Mode after continuing");
+}
+
+/// Going back a frame restores the state before each draw in turn, newest first
+#[test]
+fn previous_frame() {
+    let draw = Instruction::DrawSprite {
+        register_x: 0x0,
+        register_y: 0x1,
+        len: 5,
+    };
+    let mut chip8 = load([
+        Instruction::Clear,
+        Instruction::StoreNumberInRegister {
+            number: 1,
+            register: 0x0,
+        },
+        draw,
+        Instruction::StoreNumberInRegister {
+            number: 2,
+            register: 0x1,
+        },
+        draw,
+        Instruction::StoreNumberInRegister {
+            number: 3,
+            register: 0x2,
+        },
+    ]);
+    chip8.frame_history_len = 8;
+    for _ in 0..6 {
+        chip8.step_cycle().unwrap();
+    }
+    chip8.mode = Mode::Paused;
+
+    assert!(
+        chip8.previous_frame().unwrap(),
+        "The second draw is recorded"
+    );
+    assert_eq!(chip8.pc, chip8::PC_INIT + 8, "PC before the second draw");
+    assert_eq!(chip8.registers[0x1], 2, "V1 before the second draw");
+    assert_eq!(chip8.registers[0x2], 0, "V2 before the second draw");
+    assert_eq!(chip8.pixel_count(), 14, "Pixels before the second draw");
+    assert_eq!(chip8.mode, Mode::Paused, "Mode after going back");
+
+    assert!(
+        chip8.previous_frame().unwrap(),
+        "The first draw is recorded"
+    );
+    assert_eq!(chip8.pc, chip8::PC_INIT + 4, "PC before the first draw");
+    assert_eq!(chip8.registers[0x1], 0, "V1 before the first draw");
+    assert_eq!(chip8.pixel_count(), 0, "Pixels before the first draw");
+
+    assert!(chip8.previous_frame().unwrap(), "Clearing is recorded");
+    assert_eq!(chip8.pc, chip8::PC_INIT, "PC before clearing");
+    assert!(
+        !chip8.previous_frame().unwrap(),
+        "There should be no draw before clearing the screen"
+    );
+}
+
+/// A ROM that calls a subroutine which jumps back instead of returning leaks a stack frame on
+/// every round, which the stats report at the end of the run
+#[test]
+fn call_stats() {
+    let start = u16::try_from(chip8::PC_INIT).unwrap();
+    let mut chip8 = load([
+        Instruction::ExecuteSubroutine { address: start + 4 },
+        Instruction::Return,
+        // the inner call returns, but the outer one jumps back to the start instead
+        Instruction::ExecuteSubroutine { address: start + 2 },
+        Instruction::JumpToAddress { address: start },
+    ]);
+    assert!(
+        chip8.call_stats().unbalanced_warning().is_none(),
+        "No warning before running"
+    );
+
+    // two rounds of call, call, return, jump
+    for _ in 0..8 {
+        chip8.step_cycle().unwrap();
+    }
+    let stats = chip8.call_stats();
+    assert_eq!(stats.calls, 4);
+    assert_eq!(stats.returns, 2);
+    assert_eq!(stats.depth, 2);
+    assert_eq!(stats.max_depth, 3);
+    assert!(
+        stats.unbalanced_warning().is_some(),
+        "A leaking ROM should be warned about"
+    );
+}
+
+/// A snapshot from a file that points outside of the registers or memory is rejected instead of
+/// crashing the interpreter later, and a display wait survives saving and restoring
+#[test]
+fn restore_checks() {
+    let mut chip8 = load([Instruction::Clear]);
+    let memory_len = chip8.memory.len();
+
+    let snapshot = chip8.snapshot();
+    let pc_outside = Snapshot {
+        pc: memory_len,
+        ..snapshot.clone()
+    };
+    let return_outside = Snapshot {
+        stack: vec![memory_len + 2],
+        ..snapshot.clone()
+    };
+    let missing_register = Snapshot {
+        waiting_for_key: Some(16),
+        ..snapshot
+    };
+    for (what, snapshot) in [
+        ("PC outside of memory", pc_outside),
+        ("Return address outside of memory", return_outside),
+        ("Key stored in V16", missing_register),
+    ] {
+        assert!(
+            chip8.restore(&snapshot).is_err(),
+            "{what} should be rejected"
+        );
+        assert_eq!(chip8.pc, chip8::PC_INIT, "PC after rejecting {what}");
+    }
+
+    chip8.quirks.display_wait = true;
+    chip8.execute_raw(0xD001).unwrap();
+    let saved = serde_json::to_string(&chip8.snapshot()).unwrap();
+    let mut restored = Chip8::new();
+    restored
+        .restore(&serde_json::from_str(&saved).unwrap())
+        .unwrap();
+    assert!(
+        restored.waiting_for_vblank,
+        "Waiting for the display after restoring"
+    );
+}
@@ -0,0 +1,593 @@
+mod common;
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use chip8stuff::chip8::{
+    self,
+    instructions::{assemble, Instruction},
+    quirks::{Collision, DrawMode},
+    Chip8,
+};
+use common::{load, run_program, DATA_ADDRESS};
+
+/// The font character "0" drawn at the bottom right corner is clipped or wraps around
+#[test]
+fn sprite_wrapping() {
+    let corner = [
+        Instruction::StoreNumberInRegister {
+            number: 62,
+            register: 0x0,
+        },
+        Instruction::StoreNumberInRegister {
+            number: 30,
+            register: 0x1,
+        },
+        Instruction::LoadFontCharacter { register_x: 0x2 },
+        Instruction::DrawSprite {
+            register_x: 0x0,
+            register_y: 0x1,
+            len: 5,
+        },
+    ];
+    for (what, wrap_x, wrap_y, pixels) in [
+        ("Pixels of a clipped sprite", false, false, 3),
+        ("Pixels of a sprite wrapped horizontally", true, false, 6),
+        ("Pixels of a sprite wrapped vertically", false, true, 7),
+        ("Pixels of a sprite wrapped on both axes", true, true, 14),
+    ] {
+        let chip8 = run_program(&corner, |chip8| {
+            chip8.quirks.wrap_x = wrap_x;
+            chip8.quirks.wrap_y = wrap_y;
+        })
+        .unwrap();
+        assert_eq!(chip8.pixel_count(), pixels, "{what}");
+    }
+}
+
+/// A sprite that starts past the bottom right corner always starts at the wrapped around
+/// position, the wrap quirks only decide what happens to pixels past the edge
+#[test]
+fn sprite_start_wrapping() {
+    let past_corner = |x, y| {
+        [
+            Instruction::StoreNumberInRegister {
+                number: x,
+                register: 0x0,
+            },
+            Instruction::StoreNumberInRegister {
+                number: y,
+                register: 0x1,
+            },
+            Instruction::LoadFontCharacter { register_x: 0x2 },
+            Instruction::DrawSprite {
+                register_x: 0x0,
+                register_y: 0x1,
+                len: 5,
+            },
+        ]
+    };
+
+    let chip8 = run_program(&past_corner(68, 34), |_| {}).unwrap();
+    assert_eq!(
+        chip8.pixel_count(),
+        14,
+        "Pixels of a sprite started at 68,34"
+    );
+    assert_eq!(
+        chip8::vram_sprites(&chip8.vram, 4, 2, 8, 5),
+        [[0xF0, 0x90, 0x90, 0x90, 0xF0]],
+        "Sprite started at 68,34 drawn at 4,2"
+    );
+
+    // 126,62 wraps around to the corner at 62,30, where only 2x2 pixels of the "0" fit
+    for (what, wrap_x, wrap_y, pixels) in [
+        ("Pixels of a clipped sprite", false, false, 3),
+        ("Pixels of a sprite wrapped horizontally", true, false, 6),
+        ("Pixels of a sprite wrapped vertically", false, true, 7),
+        ("Pixels of a sprite wrapped on both axes", true, true, 14),
+    ] {
+        let chip8 = run_program(&past_corner(126, 62), |chip8| {
+            chip8.quirks.wrap_x = wrap_x;
+            chip8.quirks.wrap_y = wrap_y;
+        })
+        .unwrap();
+        assert_eq!(chip8.pixel_count(), pixels, "{what} started at 126,62");
+    }
+}
+
+/// A 15 byte sprite drawn at y=0x1E only keeps the two rows above the bottom edge, or wraps
+/// the other 13 rows around to the top
+#[test]
+fn tall_sprite_at_bottom() {
+    let tall_sprite = [
+        Instruction::StoreNumberInRegister {
+            number: 0x1E,
+            register: 0x1,
+        },
+        Instruction::SetAddressRegister {
+            address: DATA_ADDRESS,
+        },
+        Instruction::DrawSprite {
+            register_x: 0x0,
+            register_y: 0x1,
+            len: 15,
+        },
+    ];
+    let fill_tall_sprite = |chip8: &mut Chip8| {
+        let data = usize::from(DATA_ADDRESS);
+        chip8.memory[data..data + 15].fill(0xFF);
+    };
+
+    let chip8 = run_program(&tall_sprite, fill_tall_sprite).unwrap();
+    assert_eq!(chip8.pixel_count(), 16, "Pixels of a clipped tall sprite");
+    assert_eq!(
+        chip8::vram_sprites(&chip8.vram, 0, 0x1E, 8, 2),
+        [[0xFF, 0xFF]],
+        "Rows above the bottom edge"
+    );
+
+    let chip8 = run_program(&tall_sprite, |chip8| {
+        fill_tall_sprite(chip8);
+        chip8.quirks.wrap_y = true;
+    })
+    .unwrap();
+    assert_eq!(chip8.pixel_count(), 120, "Pixels of a wrapped tall sprite");
+    assert_eq!(
+        chip8::vram_sprites(&chip8.vram, 0, 0, 8, 13),
+        [[0xFF; 13]],
+        "Rows wrapped around to the top"
+    );
+}
+
+/// The same font character drawn twice
+const DRAW_TWICE: [Instruction; 3] = [
+    Instruction::LoadFontCharacter { register_x: 0x0 },
+    Instruction::DrawSprite {
+        register_x: 0x0,
+        register_y: 0x0,
+        len: 5,
+    },
+    Instruction::DrawSprite {
+        register_x: 0x0,
+        register_y: 0x0,
+        len: 5,
+    },
+];
+
+/// Drawing the same sprite twice erases it, unless pixels are combined differently
+#[test]
+fn draw_mode() {
+    let chip8 = run_program(&DRAW_TWICE, |_| {}).unwrap();
+    assert_eq!(
+        chip8.pixel_count(),
+        0,
+        "Pixels after drawing twice with XOR"
+    );
+    assert_eq!(chip8.registers[0xF], 1, "VF after drawing twice with XOR");
+
+    let chip8 = run_program(&DRAW_TWICE, |chip8| chip8.quirks.draw_mode = DrawMode::Or).unwrap();
+    assert_eq!(
+        chip8.pixel_count(),
+        14,
+        "Pixels after drawing twice with OR"
+    );
+    assert_eq!(chip8.registers[0xF], 0, "VF after drawing twice with OR");
+}
+
+/// A sprite drawn over another one is combined with it according to the draw mode, pixels
+/// outside of the sprite are kept
+#[test]
+fn overlapping_draw_modes() {
+    let overlapping = [
+        Instruction::SetAddressRegister {
+            address: DATA_ADDRESS + 1,
+        },
+        Instruction::DrawSprite {
+            register_x: 0x1,
+            register_y: 0x0,
+            len: 1,
+        },
+    ];
+    // 11111111 drawn at x=0 with XOR, then 11110000 at x=2 with the draw mode
+    let draw_first_sprite = |chip8: &mut Chip8| {
+        let data = usize::from(DATA_ADDRESS);
+        chip8.memory[data..data + 2].copy_from_slice(&[0xFF, 0xF0]);
+        chip8.address_register = DATA_ADDRESS;
+        chip8.registers[0x1] = 2;
+        chip8.execute_raw(0xD001).unwrap();
+    };
+    for (draw_mode, expected_row, expected_vf) in [
+        (DrawMode::Xor, [0b1100_0011, 0b0000_0000], 1),
+        (DrawMode::Or, [0b1111_1111, 0b0000_0000], 0),
+        (DrawMode::And, [0b1111_1100, 0b0000_0000], 1),
+    ] {
+        let chip8 = run_program(&overlapping, |chip8| {
+            draw_first_sprite(chip8);
+            chip8.quirks.draw_mode = draw_mode;
+        })
+        .unwrap();
+        assert_eq!(
+            chip8::vram_sprites(&chip8.vram, 0, 0, 16, 1),
+            [[expected_row[0]], [expected_row[1]]],
+            "Row after drawing overlapping sprites with {draw_mode}"
+        );
+        assert_eq!(
+            chip8.registers[0xF], expected_vf,
+            "VF after drawing overlapping sprites with {draw_mode}"
+        );
+    }
+}
+
+/// Which collision rule sets VF only matters when pixels are not combined with XOR
+#[test]
+fn collision_rule() {
+    for (draw_mode, collision, expected_vf) in [
+        (DrawMode::Xor, Collision::Erased, 1),
+        (DrawMode::Xor, Collision::Overlap, 1),
+        (DrawMode::Or, Collision::Erased, 0),
+        (DrawMode::Or, Collision::Overlap, 1),
+    ] {
+        let chip8 = run_program(&DRAW_TWICE, |chip8| {
+            chip8.quirks.draw_mode = draw_mode;
+            chip8.quirks.collision = collision;
+        })
+        .unwrap();
+        assert_eq!(
+            chip8.registers[0xF], expected_vf,
+            "VF after drawing twice with {draw_mode} and {collision} collisions"
+        );
+    }
+}
+
+/// DXY0 draws nothing, or a 16x16 sprite
+#[test]
+fn large_sprites() {
+    let large_sprite = [
+        Instruction::SetAddressRegister {
+            address: DATA_ADDRESS,
+        },
+        Instruction::DrawSprite {
+            register_x: 0x0,
+            register_y: 0x0,
+            len: 0,
+        },
+    ];
+    let fill_large_sprite = |chip8: &mut Chip8| {
+        let data = usize::from(DATA_ADDRESS);
+        chip8.memory[data..data + 32].fill(0xFF);
+    };
+
+    let chip8 = run_program(&large_sprite, fill_large_sprite).unwrap();
+    assert_eq!(
+        chip8.pixel_count(),
+        0,
+        "Pixels of DXY0 without large sprites"
+    );
+
+    let chip8 = run_program(&large_sprite, |chip8| {
+        fill_large_sprite(chip8);
+        chip8.quirks.large_sprites = true;
+    })
+    .unwrap();
+    assert_eq!(chip8.pixel_count(), 256, "Pixels of a 16x16 sprite");
+}
+
+/// A sprite that reaches past the end of memory fails, or only the rows inside of memory are
+/// drawn
+#[test]
+fn clamp_sprite_reads() {
+    let past_memory_end = [
+        Instruction::SetAddressRegister { address: 0xFFE },
+        Instruction::DrawSprite {
+            register_x: 0x0,
+            register_y: 0x0,
+            len: 5,
+        },
+    ];
+    let fill_memory_end = |chip8: &mut Chip8| chip8.memory[0xFFE..].fill(0xFF);
+    assert!(run_program(&past_memory_end, fill_memory_end).is_err());
+
+    let chip8 = run_program(&past_memory_end, |chip8| {
+        fill_memory_end(chip8);
+        chip8.quirks.clamp_sprite_reads = true;
+    })
+    .unwrap();
+    assert_eq!(chip8.pixel_count(), 16, "Pixels of a clamped sprite");
+}
+
+/// Drawing waits for the next timer tick, but only with the display wait quirk
+#[test]
+fn display_wait() {
+    for display_wait in [false, true] {
+        let mut chip8 = Chip8::new();
+        chip8.quirks.display_wait = display_wait;
+        chip8.execute_raw(0xD001).unwrap();
+        assert_eq!(chip8.waiting_for_vblank, display_wait);
+
+        while !chip8.count_timer_cycle(2) {}
+        assert!(!chip8.waiting_for_vblank, "Waiting after a timer tick");
+    }
+}
+
+/// A step reports whether it changed the screen and whether the draw collided
+#[test]
+fn step_outcome() {
+    let draw = Instruction::DrawSprite {
+        register_x: 0x0,
+        register_y: 0x0,
+        len: 5,
+    };
+    let mut chip8 = load([
+        Instruction::AddToRegister {
+            register: 0x1,
+            value: 1,
+        },
+        draw,
+        draw,
+    ]);
+
+    let add = chip8.step_cycle().unwrap();
+    assert!(!add.redrew, "ADD redrew");
+    assert!(!add.collision, "ADD collided");
+
+    let first_draw = chip8.step_cycle().unwrap();
+    assert_eq!(first_draw.instruction, draw);
+    assert!(first_draw.redrew, "DRW redrew");
+    assert!(!first_draw.collision, "First DRW collided");
+
+    let second_draw = chip8.step_cycle().unwrap();
+    assert!(second_draw.collision, "Second DRW collided");
+}
+
+/// A `DXY0` that is ignored without large sprites draws nothing and collides with nothing, even
+/// when VF is still 1 from an earlier instruction
+#[test]
+fn ignored_draw_collision() {
+    let mut chip8 = load([
+        Instruction::StoreNumberInRegister {
+            number: 1,
+            register: 0xF,
+        },
+        Instruction::DrawSprite {
+            register_x: 0x0,
+            register_y: 0x0,
+            len: 0,
+        },
+    ]);
+    chip8.step_cycle().unwrap();
+
+    let ignored_draw = chip8.step_cycle().unwrap();
+    assert!(!ignored_draw.redrew, "Ignored DXY0 redrew");
+    assert!(!ignored_draw.collision, "Ignored DXY0 collided");
+    assert_eq!(chip8.registers[0xF], 1, "VF after an ignored DXY0");
+}
+
+/// Exporting a drawn glyph gives back its font bytes, shifted by its position in the column
+#[test]
+fn vram_sprites() {
+    // I points to the glyph of 0 in the font
+    let chip8 = run_program(
+        &[
+            Instruction::StoreNumberInRegister {
+                number: 2,
+                register: 0x0,
+            },
+            Instruction::StoreNumberInRegister {
+                number: 1,
+                register: 0x1,
+            },
+            Instruction::DrawSprite {
+                register_x: 0x0,
+                register_y: 0x1,
+                len: 5,
+            },
+        ],
+        |_| {},
+    )
+    .unwrap();
+
+    for (what, x, width, height, expected) in [
+        (
+            "Sprite at the glyph position",
+            2,
+            8,
+            5,
+            vec![vec![0xF0, 0x90, 0x90, 0x90, 0xF0]],
+        ),
+        (
+            "Sprite split into two columns",
+            0,
+            12,
+            5,
+            vec![vec![0x3C, 0x24, 0x24, 0x24, 0x3C], vec![0; 5]],
+        ),
+        ("Sprite narrower than 8 pixels", 2, 2, 1, vec![vec![0xC0]]),
+    ] {
+        let sprites = chip8::vram_sprites(&chip8.vram, x, 1, width, height);
+        assert_eq!(sprites, expected, "{what}");
+    }
+}
+
+/// A screen set from outside is read back unchanged and is redrawn, wrong sizes are rejected
+#[test]
+fn set_vram() {
+    let drawn = run_program(&DRAW_TWICE[..2], |_| {}).unwrap();
+
+    let mut chip8 = Chip8::new();
+    let version = chip8.vram_version;
+    chip8.set_vram(drawn.vram()).unwrap();
+    assert_eq!(chip8.vram(), drawn.vram());
+    assert_ne!(
+        chip8.vram_version, version,
+        "Setting the screen should redraw"
+    );
+
+    assert!(chip8.set_vram(&drawn.vram()[1..]).is_err());
+    assert_eq!(
+        chip8.vram(),
+        drawn.vram(),
+        "A failed set changed the screen"
+    );
+}
+
+/// Text art becomes the sprite bytes `DXYN` expects, and draws the same picture again
+#[test]
+fn sprite_from_art() {
+    let smiley = chip8::sprite_from_art(
+        "
+        ..####..
+        .#....#.
+        #.#..#.#
+        #......#
+        #.#..#.#
+        #..##..#
+        .#....#.
+        ..####..
+        ",
+    )
+    .unwrap();
+    assert_eq!(smiley, [0x3C, 0x42, 0xA5, 0x81, 0xA5, 0x99, 0x42, 0x3C]);
+
+    let mut chip8 = Chip8::new();
+    let mut rom = assemble([
+        Instruction::SetAddressRegister {
+            address: u16::try_from(chip8::PC_INIT).unwrap() + 4,
+        },
+        Instruction::DrawSprite {
+            register_x: 0x0,
+            register_y: 0x0,
+            len: 8,
+        },
+    ]);
+    rom.extend(&smiley);
+    chip8.load_rom_bytes(&rom).unwrap();
+    chip8.step_cycle().unwrap();
+    chip8.step_cycle().unwrap();
+    assert_eq!(chip8::vram_sprites(chip8.vram(), 0, 0, 8, 8)[0], smiley);
+
+    let large = chip8::sprite_from_art(&"################\n".repeat(2)).unwrap();
+    assert_eq!(large, [0xFF; 4], "16 pixel wide rows");
+
+    for (art, what) in [
+        ("#######", "Rows of 7 pixels"),
+        ("########\n#######", "Rows of different widths"),
+        ("###x####", "Other characters than # and ."),
+        ("", "No rows"),
+    ] {
+        assert!(
+            chip8::sprite_from_art(art).is_err(),
+            "{what} should be rejected"
+        );
+    }
+}
+
+/// A renderer on another thread that locks the interpreter whenever the vram version changed, like
+/// the frontend does, only ever sees the screen of a complete sprite: the packed screen in the
+/// state hash is always one of the states between instructions
+#[test]
+fn complete_frames() {
+    let draw_loop = u16::try_from(chip8::PC_INIT).unwrap() + 2;
+    let program = [
+        Instruction::SetAddressRegister {
+            address: DATA_ADDRESS,
+        },
+        // a 8x15 block that is drawn and erased again
+        Instruction::DrawSprite {
+            register_x: 0x0,
+            register_y: 0x0,
+            len: 15,
+        },
+        Instruction::JumpToAddress { address: draw_loop },
+    ];
+    let prepare = |chip8: &mut Chip8| {
+        let data = usize::from(DATA_ADDRESS);
+        chip8.memory[data..data + 15].fill(0xFF);
+    };
+
+    // drawing and erasing the block, each followed by the jump, repeats after 4 instructions
+    let mut reference = load(program);
+    prepare(&mut reference);
+    reference.step_cycle().unwrap();
+    let complete_states: Vec<u64> = (0..4)
+        .map(|_| {
+            reference.step_cycle().unwrap();
+            reference.state_hash()
+        })
+        .collect();
+
+    let mut chip8 = load(program);
+    prepare(&mut chip8);
+    let chip8 = Mutex::new(chip8);
+    let steps = 10_000;
+    let done = AtomicBool::new(false);
+
+    let frames = thread::scope(|scope| {
+        scope.spawn(|| {
+            for _ in 0..steps {
+                chip8.lock().unwrap().step_cycle().unwrap();
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+
+        let mut rendered_vram_version = 0;
+        let mut frames = 0;
+        // the last pass sees the final screen, so at least one frame is rendered
+        loop {
+            let finished = done.load(Ordering::Relaxed);
+            let chip8 = chip8.lock().unwrap();
+            if chip8.vram_version != rendered_vram_version {
+                assert!(
+                    complete_states.contains(&chip8.state_hash()),
+                    "Rendered a screen that is not between two instructions"
+                );
+                rendered_vram_version = chip8.vram_version;
+                frames += 1;
+            }
+            if finished {
+                break frames;
+            }
+        }
+    });
+
+    assert!(frames > 0, "No frame was rendered");
+    assert_eq!(
+        chip8.lock().unwrap().vram_version,
+        steps / 2,
+        "Every draw bumps the vram version once"
+    );
+}
+
+/// The test pattern is a border around a checkerboard, and is gone before the first `00E0` or
+/// `DXYN` changes the screen
+#[test]
+fn test_pattern() {
+    let pixel = |chip8: &Chip8, x, y| chip8.vram[chip8::vram_index(x, y).unwrap()];
+    let last_x = chip8::DISPLAY_WIDTH - 1;
+    let last_y = chip8::DISPLAY_HEIGHT - 1;
+
+    let mut chip8 = load([Instruction::Clear]);
+    chip8.fill_test_pattern();
+    for (x, y) in [(0, 0), (1, 0), (last_x, 5), (6, last_y), (last_x, last_y)] {
+        assert_eq!(pixel(&chip8, x, y), 1, "Border pixel {x},{y}");
+    }
+    for (x, y, expected) in [(1, 1, 1), (2, 1, 0), (1, 2, 0), (30, 10, 1), (31, 10, 0)] {
+        assert_eq!(pixel(&chip8, x, y), expected, "Checkerboard pixel {x},{y}");
+    }
+
+    chip8.step_cycle().unwrap();
+    assert_eq!(chip8.pixel_count(), 0, "Pixels after 00E0");
+
+    // the sprite is drawn on an empty screen instead of being combined with the pattern
+    let mut chip8 = load(DRAW_TWICE[..2].iter().copied());
+    chip8.fill_test_pattern();
+    chip8.step_cycle().unwrap();
+    chip8.step_cycle().unwrap();
+    assert_eq!(chip8.pixel_count(), 14, "Pixels after the first DXYN");
+    assert_eq!(chip8.registers[0xF], 0, "VF after the first DXYN");
+}
@@ -0,0 +1,317 @@
+mod common;
+
+use chip8stuff::chip8::{
+    self,
+    instructions::{assemble, supported_opcodes, Instruction},
+    quirks::PcOverflow,
+    rng::FixedRng,
+    Chip8,
+};
+use common::{run_program, DATA_ADDRESS};
+
+/// 8XY6 and 8XYE shift VY into VX, or VX in place. VF gets the shifted out bit and is written
+/// last, so it holds the flag even when it is also the destination
+#[test]
+fn shift_quirk() {
+    let right: fn(usize) -> Instruction = |register_x| Instruction::RightShiftRegister {
+        register_x,
+        register_y: 0x2,
+    };
+    let left: fn(usize) -> Instruction = |register_x| Instruction::LeftShiftRegister {
+        register_x,
+        register_y: 0x2,
+    };
+    for (name, shift, register_x, shift_uses_vy, expected, expected_vf) in [
+        ("8126 shifting VY", right, 0x1, true, 0x40, 1),
+        ("8126 shifting VX in place", right, 0x1, false, 0x01, 0),
+        ("812E shifting VY", left, 0x1, true, 0x02, 1),
+        ("812E shifting VX in place", left, 0x1, false, 0x04, 0),
+        ("8F26 shifting VY into VF", right, 0xF, true, 1, 1),
+        ("8F2E shifting VY into VF", left, 0xF, true, 1, 1),
+    ] {
+        let program = [
+            Instruction::StoreNumberInRegister {
+                number: 0x02,
+                register: 0x1,
+            },
+            Instruction::StoreNumberInRegister {
+                number: 0x81,
+                register: 0x2,
+            },
+            shift(register_x),
+        ];
+        let chip8 =
+            run_program(&program, |chip8| chip8.quirks.shift_uses_vy = shift_uses_vy).unwrap();
+        assert_eq!(chip8.registers[register_x], expected, "{name}");
+        assert_eq!(chip8.registers[0xF], expected_vf, "{name}, VF");
+        assert_eq!(chip8.registers[0x2], 0x81, "{name}, VY");
+    }
+}
+
+/// 8XY5 and 8XY7 set VF to 1 when there is no borrow, which includes equal operands
+#[test]
+fn subtraction_flags() {
+    let sub: fn(usize) -> Instruction = |register_x| Instruction::SubRegisters {
+        register_x,
+        register_y: 0x2,
+    };
+    let subn: fn(usize) -> Instruction = |register_x| Instruction::SubRegistersOtherWayArround {
+        register_x,
+        register_y: 0x2,
+    };
+    for (name, subtract, x, y, expected, expected_vf) in [
+        ("8125 without a borrow", sub, 5, 3, 2, 1),
+        ("8125 with equal operands", sub, 3, 3, 0, 1),
+        ("8125 with a borrow", sub, 3, 5, 0xFE, 0),
+        ("8127 without a borrow", subn, 3, 5, 2, 1),
+        ("8127 with equal operands", subn, 3, 3, 0, 1),
+        ("8127 with a borrow", subn, 5, 3, 0xFE, 0),
+    ] {
+        let program = [
+            Instruction::StoreNumberInRegister {
+                number: x,
+                register: 0x1,
+            },
+            Instruction::StoreNumberInRegister {
+                number: y,
+                register: 0x2,
+            },
+            subtract(0x1),
+        ];
+        let chip8 = run_program(&program, |_| {}).unwrap();
+        assert_eq!(chip8.registers[0x1], expected, "{name}");
+        assert_eq!(chip8.registers[0xF], expected_vf, "{name}, VF");
+    }
+}
+
+/// FX55 moves I behind the stored registers, or leaves it unchanged
+#[test]
+fn store_increments_address_register() {
+    let store = [
+        Instruction::SetAddressRegister {
+            address: DATA_ADDRESS,
+        },
+        Instruction::StoreRegisters { register_x: 0x2 },
+    ];
+    let chip8 = run_program(&store, |_| {}).unwrap();
+    assert_eq!(chip8.address_register, DATA_ADDRESS + 3);
+
+    let chip8 = run_program(&store, |chip8| {
+        chip8.quirks.increment_i_on_store = false;
+    })
+    .unwrap();
+    assert_eq!(chip8.address_register, DATA_ADDRESS);
+}
+
+/// B240 adds V0, or V2 when it is read as BXNN
+#[test]
+fn jump_offset_quirk() {
+    let jump_offset = 0xB240;
+    assert_eq!(
+        Instruction::try_from(jump_offset).ok(),
+        Some(Instruction::JumpOffsetV0 { address: 0x240 })
+    );
+    for (name, jump_v0_uses_vx, expected_pc) in [
+        ("PC after B240 adding V0", false, 0x241),
+        ("PC after B240 adding V2", true, 0x245),
+    ] {
+        let mut chip8 = Chip8::new();
+        chip8.registers[0x0] = 1;
+        chip8.registers[0x2] = 5;
+        chip8.quirks.jump_v0_uses_vx = jump_v0_uses_vx;
+        chip8.execute_raw(jump_offset).unwrap();
+        assert_eq!(chip8.pc, expected_pc, "{name}");
+    }
+}
+
+/// A jump past the end of memory fails, or wraps around
+#[test]
+fn jump_outside_of_memory() {
+    let mut chip8 = Chip8::new();
+    chip8.registers[0x0] = 0xFF;
+    assert!(chip8.execute_raw(0xBFFF).is_err());
+
+    chip8.quirks.pc_overflow = PcOverflow::Wrap;
+    chip8.execute_raw(0xBFFF).unwrap();
+    assert_eq!(chip8.pc, 0xFE);
+}
+
+/// A word that is no instruction fails, or is skipped and execution continues behind it
+#[test]
+fn skip_unknown_opcodes() {
+    let mut bad_word = assemble([Instruction::StoreNumberInRegister {
+        number: 1,
+        register: 0x0,
+    }]);
+    bad_word.extend([0xFF, 0xFF]);
+    bad_word.extend(assemble([Instruction::StoreNumberInRegister {
+        number: 2,
+        register: 0x1,
+    }]));
+
+    let mut chip8 = Chip8::new();
+    chip8.load_rom_bytes(&bad_word).unwrap();
+    chip8.step_cycle().unwrap();
+    assert!(chip8.step_cycle().is_err());
+
+    let mut chip8 = Chip8::new();
+    chip8.quirks.skip_unknown_opcodes = true;
+    chip8.load_rom_bytes(&bad_word).unwrap();
+    for _ in 0..3 {
+        chip8.step_cycle().unwrap();
+    }
+    assert_eq!(
+        chip8.registers[0x1], 2,
+        "Register set behind a skipped opcode"
+    );
+    assert_eq!(chip8.pc, chip8::PC_INIT + 6, "PC behind a skipped opcode");
+}
+
+/// Adding to an I close to 0xFFFF wraps around or fails
+#[test]
+fn address_register_overflow() {
+    let add_to_i = [Instruction::AddXtoI { register_x: 0x0 }];
+    let high_address_register = |chip8: &mut Chip8| {
+        chip8.address_register = 0xFFFE;
+        chip8.registers[0x0] = 3;
+    };
+    let chip8 = run_program(&add_to_i, high_address_register).unwrap();
+    assert_eq!(chip8.address_register, 1);
+
+    let result = run_program(&add_to_i, |chip8| {
+        high_address_register(chip8);
+        chip8.quirks.wrap_address_register = false;
+    });
+    assert!(result.is_err(), "Moving I outside of memory should fail");
+}
+
+/// Every opcode that can be decoded has exactly one entry in the opcode table with the same
+/// mnemonic, and no other opcode has an entry
+#[test]
+fn opcode_table() {
+    for opcode in 0..=u16::MAX {
+        let mut entries = supported_opcodes()
+            .iter()
+            .filter(|info| info.matches(opcode));
+        let entry = entries.next();
+        assert!(
+            entries.next().is_none(),
+            "0x{opcode:04X} matches several opcode table entries"
+        );
+
+        match (Instruction::try_from(opcode), entry) {
+            (Ok(instruction), Some(entry)) => {
+                let text = instruction.to_string();
+                assert_eq!(
+                    text.split(' ').next(),
+                    Some(entry.mnemonic),
+                    "0x{opcode:04X} decodes to {text}, but its table entry {} is {}",
+                    entry.pattern,
+                    entry.mnemonic
+                );
+            }
+            (Err(_), None) => {}
+            (Ok(instruction), None) => {
+                panic!("0x{opcode:04X} decodes to {instruction}, but has no table entry");
+            }
+            (Err(_), Some(entry)) => {
+                panic!(
+                    "0x{opcode:04X} matches the table entry {}, but can't be decoded",
+                    entry.pattern
+                );
+            }
+        }
+    }
+}
+
+/// Undecodable words are an error for the executor, but [`Instruction::Unknown`] for tools
+#[test]
+fn lenient_decoding() {
+    assert!(Instruction::try_from(0xFFFF).is_err());
+    assert_eq!(
+        Instruction::try_from_lenient(0xFFFF),
+        Instruction::Unknown(0xFFFF)
+    );
+    assert_eq!(Instruction::try_from_lenient(0x00E0), Instruction::Clear);
+    assert_eq!(u16::from(Instruction::Unknown(0x1234)), 0x1234);
+    assert_eq!(Instruction::Unknown(0xFFFF).to_string(), "DB 0xFF, 0xFF");
+}
+
+/// Single opcodes execute without being fetched from memory, the PC only changes by jumps and
+/// skips
+#[test]
+fn execute_raw() {
+    let mut chip8 = Chip8::new();
+
+    chip8.execute_raw(0x6A02).unwrap();
+    assert_eq!(chip8.registers[0xA], 0x02, "6A02 sets VA");
+    assert_eq!(chip8.pc, chip8::PC_INIT, "6A02 keeps the PC");
+
+    chip8.execute_raw(0x8AA4).unwrap();
+    assert_eq!(chip8.registers[0xA], 0x04, "8AA4 doubles VA");
+
+    chip8.execute_raw(0xA123).unwrap();
+    assert_eq!(chip8.address_register, 0x123, "A123 sets I");
+
+    chip8.execute_raw(0x3A04).unwrap();
+    assert_eq!(chip8.pc, chip8::PC_INIT + 2, "3A04 skips");
+
+    chip8.execute_raw(0x1300).unwrap();
+    assert_eq!(chip8.pc, 0x300, "1300 jumps");
+
+    chip8.execute_raw(0x2400).unwrap();
+    assert_eq!(chip8.pc, 0x400, "2400 calls");
+    chip8.execute_raw(0x00EE).unwrap();
+    assert_eq!(chip8.pc, 0x300, "00EE returns");
+
+    let hash = chip8.state_hash();
+    assert!(
+        chip8.execute_raw(0xFFFF).is_err(),
+        "FFFF is not an instruction"
+    );
+    assert!(chip8.execute_raw(0x00EE).is_err(), "00EE without a call");
+    assert_eq!(chip8.state_hash(), hash, "Failed opcodes change nothing");
+}
+
+/// `CXNN` masks the numbers of the random source, a [`FixedRng`] makes them predictable
+#[test]
+fn fixed_rng() {
+    let mut chip8 = Chip8::new();
+    chip8.set_rng(FixedRng::new(vec![0xAB, 0xFF]));
+
+    chip8.execute_raw(0xC0FF).unwrap();
+    assert_eq!(chip8.registers[0x0], 0xAB, "First random number");
+    chip8.execute_raw(0xC10F).unwrap();
+    assert_eq!(chip8.registers[0x1], 0x0F, "Second random number, masked");
+    chip8.execute_raw(0xC2F0).unwrap();
+    assert_eq!(chip8.registers[0x2], 0xA0, "Random numbers start over");
+}
+
+/// `FX33` and `FX55` mark the bytes they write, loading the ROM doesn't
+#[test]
+fn written_memory() {
+    let chip8 = run_program(
+        &[
+            Instruction::SetAddressRegister {
+                address: DATA_ADDRESS,
+            },
+            Instruction::BinaryCodedDecimal { register_x: 0x0 },
+            Instruction::SetAddressRegister {
+                address: DATA_ADDRESS + 3,
+            },
+            Instruction::StoreRegisters { register_x: 0x1 },
+        ],
+        |_| {},
+    )
+    .unwrap();
+
+    let written: Vec<usize> = chip8
+        .written_memory()
+        .iter()
+        .enumerate()
+        .filter(|(_, written)| **written)
+        .map(|(address, _)| address)
+        .collect();
+    let data = usize::from(DATA_ADDRESS);
+    assert_eq!(written, [data, data + 1, data + 2, data + 3, data + 4]);
+}
@@ -0,0 +1,189 @@
+mod common;
+
+use chip8stuff::chip8::{self, instructions::Instruction, rng::FixedRng, Chip8, Mode};
+use common::load;
+
+/// A burst of key events resolves `FX0A` with the first key that was released, in queue order
+#[test]
+fn key_events() {
+    let mut chip8 = load([Instruction::WaitForKey { register_x: 0x3 }]);
+    chip8.step_cycle().unwrap();
+    assert_eq!(chip8.waiting_for_key, Some(0x3), "Waiting after FX0A");
+
+    // 0x7 was never pressed and 0x20 doesn't exist, so only the release of 0x5 counts
+    for (key, down) in [
+        (0x2, true),
+        (0x7, false),
+        (0x5, true),
+        (0x20, false),
+        (0x5, false),
+        (0x2, false),
+        (0xC, true),
+    ] {
+        chip8.queue_key_event(key, down);
+    }
+    assert_eq!(
+        chip8.waiting_for_key,
+        Some(0x3),
+        "Waiting before processing"
+    );
+
+    chip8.process_key_events();
+    assert_eq!(chip8.waiting_for_key, None, "Waiting after processing");
+    assert_eq!(chip8.registers[0x3], 0x5, "Key stored by FX0A");
+    assert_eq!(chip8.keys(), 1 << 0xC, "Keys down after the burst");
+}
+
+/// Two input sources merged into one mask only report a release once neither holds the key,
+/// and a key released by either of them resolves `FX0A`
+#[test]
+fn merged_key_sources() {
+    let mut chip8 = load([Instruction::WaitForKey { register_x: 0x3 }]);
+    chip8.step_cycle().unwrap();
+
+    let physical = 1 << 0x5;
+    let mut virtual_keys = 0;
+    chip8.queue_key_changes(0, physical);
+
+    // the virtual keypad presses and releases the key the keyboard holds
+    for pressed in [1 << 0x5, 0] {
+        chip8.queue_key_changes(physical | virtual_keys, physical | pressed);
+        virtual_keys = pressed;
+    }
+    chip8.process_key_events();
+    assert_eq!(chip8.waiting_for_key, Some(0x3), "Waiting while held");
+    assert_eq!(chip8.keys(), physical, "Keys down while held");
+
+    // a virtual key released on its own ends the wait
+    chip8.queue_key_changes(physical, physical | (1 << 0xB));
+    chip8.queue_key_changes(physical | (1 << 0xB), physical);
+    chip8.process_key_events();
+    assert_eq!(chip8.waiting_for_key, None, "Waiting after a virtual key");
+    assert_eq!(chip8.registers[0x3], 0xB, "Key stored from a virtual key");
+    assert_eq!(chip8.keys(), physical, "Keys down after a virtual key");
+}
+
+/// Pausing while `FX0A` waits keeps waiting, a key released while paused ends the wait and
+/// resuming continues behind the `FX0A`
+#[test]
+fn pause_while_waiting() {
+    let mut chip8 = load([
+        Instruction::WaitForKey { register_x: 0x3 },
+        Instruction::StoreNumberInRegister {
+            number: 1,
+            register: 0x4,
+        },
+    ]);
+    chip8.step_cycle().unwrap();
+
+    chip8.mode = Mode::Paused;
+    assert_eq!(chip8.waiting_for_key, Some(0x3), "Waiting after pausing");
+    let snapshot = chip8.snapshot();
+    assert_eq!(snapshot.waiting_for_key, Some(0x3), "Waiting in a snapshot");
+
+    chip8.queue_key_event(0x5, true);
+    chip8.queue_key_event(0x5, false);
+    chip8.process_key_events();
+    assert_eq!(chip8.mode, Mode::Paused, "Mode after a key while paused");
+    assert_eq!(chip8.waiting_for_key, None, "Waiting after a key");
+    assert_eq!(chip8.registers[0x3], 0x5, "Key stored while paused");
+
+    chip8.mode = Mode::Running;
+    chip8.step_cycle().unwrap();
+    assert_eq!(chip8.registers[0x4], 1, "Register set after resuming");
+
+    // restoring the snapshot waits again
+    chip8.restore(&snapshot).unwrap();
+    assert_eq!(chip8.waiting_for_key, Some(0x3), "Waiting after restoring");
+    assert_eq!(chip8.mode, Mode::Paused, "Mode after restoring");
+}
+
+/// Keys > 0xF don't exist, so they are up even when every key is down: `EX9E` never skips and
+/// `EXA1` always does
+#[test]
+fn missing_keys() {
+    for (name, instruction, expected_pc) in [
+        (
+            "PC after EX9E with 0x10",
+            Instruction::SkipIfKey { register_x: 0x1 },
+            chip8::PC_INIT,
+        ),
+        (
+            "PC after EXA1 with 0x10",
+            Instruction::SkipIfNotKey { register_x: 0x1 },
+            chip8::PC_INIT + 2,
+        ),
+    ] {
+        let mut chip8 = Chip8::new().with_register(0x1, 0x10).unwrap();
+        chip8.set_keys(u16::MAX);
+        chip8.execute_raw(u16::from(instruction)).unwrap();
+        assert_eq!(chip8.pc, expected_pc, "{name}");
+    }
+}
+
+/// Replaying the same recorded key masks with the same random numbers ends in the same state,
+/// a different recording doesn't
+#[test]
+fn replay_keys() {
+    let start = u16::try_from(chip8::PC_INIT).unwrap();
+    // while key 1 is down, random numbers are summed up in V2
+    let program = [
+        Instruction::StoreNumberInRegister {
+            number: 0x1,
+            register: 0x1,
+        },
+        Instruction::SkipIfKey { register_x: 0x1 },
+        Instruction::JumpToAddress {
+            address: start + 10,
+        },
+        Instruction::RandomNumber {
+            register_x: 0x0,
+            mask: 0xFF,
+        },
+        Instruction::AddRegisters {
+            register_x: 0x2,
+            register_y: 0x0,
+        },
+        Instruction::AddToRegister {
+            register: 0x4,
+            value: 1,
+        },
+        Instruction::JumpToAddress { address: start + 2 },
+    ];
+    let replay = |recording: &[u16]| {
+        let mut chip8 = load(program);
+        chip8.set_rng(FixedRng::new(vec![0x12, 0x34, 0x56, 0x78]));
+        for keys in recording {
+            chip8.set_keys(*keys);
+            assert_eq!(chip8.keys(), *keys, "Keys after setting them");
+            chip8.step_cycle().unwrap();
+        }
+        chip8
+    };
+
+    // one mask per instruction, key 1 is held for the first two rounds of the loop
+    let recording: Vec<u16> = (0..31)
+        .map(|step| if step < 11 { 1 << 0x1 } else { 1 << 0x7 })
+        .collect();
+    let first = replay(&recording);
+    let second = replay(&recording);
+    assert_eq!(
+        first.state_hash(),
+        second.state_hash(),
+        "State after the same replay"
+    );
+    assert_eq!(
+        first.registers[0x2],
+        0x12 + 0x34,
+        "Sum of the random numbers while key 1 was held"
+    );
+
+    let mut other = recording.clone();
+    // the key is checked again by the EX9E of step 23
+    other[23] = 1 << 0x1;
+    assert_ne!(
+        replay(&other).state_hash(),
+        first.state_hash(),
+        "State after a different replay"
+    );
+}
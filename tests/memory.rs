@@ -0,0 +1,190 @@
+mod common;
+
+use chip8stuff::chip8::{
+    self, error::EmuError, instructions::Instruction, platform::Platform, quirks::PcOverflow,
+    rom_info, Chip8, Mode,
+};
+use common::run_program;
+
+/// Code at the very top of memory fails, halts or wraps around to the start of memory
+#[test]
+fn pc_overflow() {
+    let top_of_memory = [Instruction::JumpToAddress { address: 0xFFE }];
+    let code_at_top = |chip8: &mut Chip8| {
+        chip8.memory[0xFFE..].copy_from_slice(&[0x60, 0x01]);
+        chip8.memory[..2].copy_from_slice(&[0x61, 0x02]);
+    };
+
+    let mut chip8 = run_program(&top_of_memory, code_at_top).unwrap();
+    chip8.step_cycle().unwrap();
+    assert!(chip8.step_cycle().is_err());
+    assert_eq!(chip8.mode, Mode::Running, "Mode after running off the end");
+
+    let mut chip8 = run_program(&top_of_memory, |chip8| {
+        code_at_top(chip8);
+        chip8.quirks.pc_overflow = PcOverflow::Halt;
+    })
+    .unwrap();
+    chip8.step_cycle().unwrap();
+    assert!(chip8.step_cycle().is_err(), "Halting should fail the step");
+    assert_eq!(chip8.mode, Mode::Halted);
+
+    let mut chip8 = run_program(&top_of_memory, |chip8| {
+        code_at_top(chip8);
+        chip8.quirks.pc_overflow = PcOverflow::Wrap;
+    })
+    .unwrap();
+    chip8.step_cycle().unwrap();
+    chip8.step_cycle().unwrap();
+    assert_eq!(chip8.registers[0x1], 0x02, "Register set after wrapping");
+    assert_eq!(chip8.pc, 2, "PC after wrapping around");
+}
+
+/// ROMs are rejected if they don't fit into the memory of the platform
+#[test]
+fn memory_size() {
+    for platform in [Platform::Chip8, Platform::XoChip] {
+        let size = platform.memory_size();
+        let available = size - chip8::PC_INIT;
+
+        let mut chip8 = Chip8::with_memory_size(size).unwrap();
+        chip8.load_rom_bytes(&vec![0xAA; available]).unwrap();
+        assert_eq!(chip8.memory[size - 1], 0xAA, "Last byte of a full memory");
+
+        let mut chip8 = Chip8::with_memory_size(size).unwrap();
+        assert!(
+            chip8.load_rom_bytes(&vec![0xAA; available + 1]).is_err(),
+            "Loading a ROM larger than the {size} bytes of memory of the {platform} platform should fail"
+        );
+    }
+
+    assert!(Chip8::with_memory_size(chip8::MAX_MEMORY_SIZE + 1).is_err());
+}
+
+/// A machine prepared with registers and memory runs single instructions, invalid targets fail
+#[test]
+fn initial_state() {
+    // 8014: ADD V0, V1
+    let mut chip8 = Chip8::new()
+        .with_register(0x0, 0xF0)
+        .unwrap()
+        .with_register(0x1, 0x20)
+        .unwrap()
+        .with_memory(chip8::PC_INIT, &[0x80, 0x14])
+        .unwrap();
+    chip8.step_cycle().unwrap();
+    assert_eq!(chip8.registers[0x0], 0x10, "V0 after adding");
+    assert_eq!(chip8.registers[0xF], 1, "VF after adding");
+
+    assert!(Chip8::new().with_register(0x10, 1).is_err(), "Setting V16");
+    let memory_len = Chip8::new().memory.len();
+    assert!(
+        Chip8::new().with_memory(memory_len - 1, &[1, 2]).is_err(),
+        "Writing past the end of memory"
+    );
+    assert!(
+        Chip8::new().with_memory(usize::MAX, &[1]).is_err(),
+        "Writing at the largest address"
+    );
+}
+
+/// Loading a ROM remembers its size and CRC-32, which matches the usual check value
+#[test]
+fn rom_info() {
+    assert_eq!(rom_info::crc32(b"123456789"), 0xCBF4_3926);
+
+    let mut chip8 = Chip8::new();
+    assert!(chip8.rom_info().is_none(), "ROM info before loading a ROM");
+
+    chip8.load_rom_bytes(b"123456789").unwrap();
+    chip8.set_rom_name("check.ch8");
+    let info = chip8.rom_info().expect("ROM info after loading a ROM");
+    assert_eq!(info.name.as_deref(), Some("check.ch8"));
+    assert_eq!(info.len, 9);
+    assert_eq!(info.crc32, 0xCBF4_3926);
+}
+
+/// An empty ROM is rejected instead of running whatever is in memory
+#[test]
+fn empty_rom() {
+    let mut chip8 = Chip8::new();
+    assert_eq!(chip8.load_rom_bytes(&[]), Err(EmuError::RomEmpty));
+    assert!(
+        chip8.rom_info().is_none(),
+        "ROM info after loading an empty ROM"
+    );
+}
+
+/// Every fault is reported as its own [`EmuError`] variant, so embedders can match on them
+#[test]
+fn emu_errors() {
+    let mut chip8 = Chip8::new();
+    let available = chip8.memory.len() - chip8::PC_INIT;
+    assert_eq!(
+        chip8.load_rom_bytes(&vec![0xAA; available + 1]),
+        Err(EmuError::RomTooLarge {
+            len: available + 1,
+            available,
+        })
+    );
+
+    let mut chip8 = Chip8::new();
+    chip8.registers[0x0] = 0xFF;
+    chip8.address_register = 0xFFF;
+    for (name, opcode, expected) in [
+        (
+            "Unknown opcode",
+            0xFFFF,
+            EmuError::UnknownOpcode {
+                raw: 0xFFFF,
+                addr: chip8::PC_INIT,
+            },
+        ),
+        ("Return without a call", 0x00EE, EmuError::StackUnderflow),
+        (
+            "Jump outside of memory",
+            0xBFFF,
+            EmuError::JumpOutOfBounds { target: 0x10FE },
+        ),
+        (
+            "Store past the end of memory",
+            0xFF55,
+            EmuError::MemoryOutOfBounds {
+                addr: 0xFFF,
+                len: 16,
+            },
+        ),
+        (
+            "Sprite past the end of memory",
+            0xD00F,
+            EmuError::MemoryOutOfBounds {
+                addr: 0xFFF,
+                len: 15,
+            },
+        ),
+    ] {
+        let result = chip8.execute_raw(opcode).map(|_| ());
+        assert_eq!(result, Err(expected), "{name}");
+    }
+
+    chip8.quirks.wrap_address_register = false;
+    chip8.address_register = 0xFFFE;
+    assert_eq!(
+        chip8.execute_raw(0xF01E).map(|_| ()),
+        Err(EmuError::AddressRegisterOutOfBounds {
+            address_register: 0xFFFE,
+            value: 0xFF,
+        })
+    );
+
+    chip8.pc = chip8.memory.len();
+    assert_eq!(
+        chip8.step_cycle().map(|_| ()),
+        Err(EmuError::PcOutOfBounds { addr: chip8.pc })
+    );
+    chip8.quirks.pc_overflow = PcOverflow::Halt;
+    assert_eq!(
+        chip8.step_cycle().map(|_| ()),
+        Err(EmuError::Halted { addr: chip8.pc })
+    );
+}
@@ -0,0 +1,25 @@
+use chip8stuff::chip8::platform::Platform;
+
+/// Detect the platform of ROMs that contain a telltale opcode
+#[test]
+fn platform_detection() {
+    let cases: [(&str, [u8; 2], Platform); 7] = [
+        ("00FF high resolution", [0x00, 0xFF], Platform::SuperChip),
+        ("00C4 scroll down", [0x00, 0xC4], Platform::SuperChip),
+        ("D120 16x16 sprite", [0xD1, 0x20], Platform::SuperChip),
+        ("F375 store flags", [0xF3, 0x75], Platform::SuperChip),
+        ("F000 long I", [0xF0, 0x00], Platform::XoChip),
+        ("00D2 scroll up", [0x00, 0xD2], Platform::XoChip),
+        ("5122 store range", [0x51, 0x22], Platform::XoChip),
+    ];
+
+    for (what, opcode, platform) in cases {
+        // behind an ordinary instruction, so the opcode is not at the start of the ROM
+        let rom = [0x60, 0x01, opcode[0], opcode[1]];
+        assert_eq!(Platform::detect(&rom), platform, "{what}");
+    }
+
+    // XO-CHIP is a superset of the SCHIP, so XO-CHIP opcodes win
+    let mixed = [0x00, 0xFF, 0xF0, 0x00];
+    assert_eq!(Platform::detect(&mixed), Platform::XoChip);
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use chip8stuff::chip8::{
+    self,
+    instructions::{assemble, Instruction},
+    run::{self, Limits, StopReason},
+    Chip8,
+};
+use common::{jump_to_itself, load};
+
+/// The sprite the logo is made of
+const SMILEY: [u8; 8] = [0x3C, 0x42, 0xA5, 0x81, 0xA5, 0x99, 0x42, 0x3C];
+
+/// A ROM that clears the screen, draws a logo of two smileys next to each other and spins
+fn logo_rom() -> Chip8 {
+    let start = u16::try_from(chip8::PC_INIT).unwrap();
+    let draw = Instruction::DrawSprite {
+        register_x: 0x0,
+        register_y: 0x1,
+        len: 8,
+    };
+    let program = [
+        Instruction::Clear,
+        Instruction::SetAddressRegister {
+            address: start + 16,
+        },
+        Instruction::StoreNumberInRegister {
+            number: 8,
+            register: 0x0,
+        },
+        Instruction::StoreNumberInRegister {
+            number: 8,
+            register: 0x1,
+        },
+        draw,
+        Instruction::StoreNumberInRegister {
+            number: 16,
+            register: 0x0,
+        },
+        draw,
+        jump_to_itself(chip8::PC_INIT + 14),
+    ];
+    let mut chip8 = load(program);
+    let data = usize::from(start) + assemble(program).len();
+    chip8.memory[data..data + SMILEY.len()].copy_from_slice(&SMILEY);
+    chip8
+}
+
+/// Running until the Nth frame stops right after the ROM changed the screen N times, clearing the
+/// screen included, and the logo is complete once both smileys are drawn
+#[test]
+fn logo_frame() {
+    for (what, until_frame, smileys, expected_stop) in [
+        ("Cleared screen", Some(1), 0_u16, StopReason::UntilFrame),
+        ("First smiley", Some(2), 1, StopReason::UntilFrame),
+        ("Logo", Some(3), 2, StopReason::UntilFrame),
+        ("Logo of an idling ROM", None, 2, StopReason::Idle),
+    ] {
+        let mut chip8 = logo_rom();
+        let result = run::run(
+            &mut chip8,
+            Limits {
+                until_frame,
+                ..Limits::cycles(1000)
+            },
+        );
+        assert_eq!(result.stop_reason, expected_stop, "{what}");
+
+        assert_eq!(
+            chip8.pixel_count(),
+            usize::from(smileys) * 26,
+            "Pixels of the {what}"
+        );
+        for smiley in 0..smileys {
+            assert_eq!(
+                chip8::vram_sprites(&chip8.vram, 8 + 8 * smiley, 8, 8, 8),
+                [SMILEY],
+                "Smiley {smiley} of the {what}"
+            );
+        }
+    }
+}
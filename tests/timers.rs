@@ -0,0 +1,257 @@
+mod common;
+
+use chip8stuff::chip8::{self, instructions::Instruction, Chip8, Mode, DELAY_TIMER_FREQUENCY};
+use common::{jump_to_itself, load, run_program};
+
+/// Instructions per second of the frontend
+const INSTRUCTION_FREQUENCY: u16 = 800;
+
+fn cycles_per_timer_tick() -> u32 {
+    chip8::cycles_per_timer_tick(f32::from(INSTRUCTION_FREQUENCY), DELAY_TIMER_FREQUENCY)
+}
+
+/// Sets the delay timer to 40, then jumps to itself
+fn set_delay_timer() -> Chip8 {
+    let program = [
+        Instruction::StoreNumberInRegister {
+            number: 40,
+            register: 0x0,
+        },
+        Instruction::SetDelayTimer { register_x: 0x0 },
+    ];
+    let mut chip8 = run_program(&program, |_| {}).unwrap();
+    chip8.memory[chip8.pc..chip8.pc + 2]
+        .copy_from_slice(&u16::from(jump_to_itself(chip8.pc)).to_be_bytes());
+    chip8
+}
+
+/// A second worth of instructions decreases the delay timer once per tick of the timer
+/// frequency, counted like the frontend does
+#[test]
+fn timer_frequency() {
+    for (timer_frequency, cycles_per_tick, expected_timer) in
+        [(60.0, 13, 40 - 61), (30.0, 26, 40 - 30), (1.0, 800, 40 - 1)]
+    {
+        let cycles_per_timer_tick =
+            chip8::cycles_per_timer_tick(f32::from(INSTRUCTION_FREQUENCY), timer_frequency);
+        assert_eq!(
+            cycles_per_timer_tick, cycles_per_tick,
+            "Instructions per tick at {timer_frequency} Hz"
+        );
+
+        let mut chip8 = set_delay_timer();
+        for _ in 0..INSTRUCTION_FREQUENCY {
+            chip8.step_cycle().unwrap();
+            chip8.count_timer_cycle(cycles_per_timer_tick);
+        }
+        assert_eq!(
+            i32::from(chip8.delay_timer),
+            expected_timer.max(0),
+            "Delay timer after a second at {timer_frequency} Hz"
+        );
+
+        // the frontend counts a cycle without an instruction while waiting for a key,
+        // so a second of waiting takes as many ticks as a second of instructions
+        chip8.delay_timer = 40;
+        chip8.timer_cycles = 0;
+        chip8.waiting_for_key = Some(0x1);
+        for _ in 0..INSTRUCTION_FREQUENCY {
+            chip8.count_timer_cycle(cycles_per_timer_tick);
+        }
+        assert_eq!(
+            i32::from(chip8.delay_timer),
+            expected_timer.max(0),
+            "Delay timer after a second of waiting at {timer_frequency} Hz"
+        );
+    }
+}
+
+/// A saved and restored state keeps the timers, their phase and the mode, so the restored timer
+/// ticks at the same instruction as the original one
+#[test]
+fn save_state_timers() -> anyhow::Result<()> {
+    let cycles_per_timer_tick = cycles_per_timer_tick();
+
+    let mut chip8 = set_delay_timer();
+    for _ in 0..100 {
+        chip8.step_cycle()?;
+        chip8.count_timer_cycle(cycles_per_timer_tick);
+    }
+    chip8.mode = Mode::Paused;
+    chip8.waiting_for_key = Some(0x3);
+
+    let saved = serde_json::to_string(&chip8.snapshot())?;
+    let mut restored = Chip8::new();
+    restored.restore(&serde_json::from_str(&saved)?)?;
+
+    assert_eq!(restored.delay_timer, 40 - 7);
+    assert_eq!(restored.timer_cycles, 100 % 13);
+    assert_eq!(restored.mode, Mode::Paused);
+    assert_eq!(restored.waiting_for_key, Some(0x3));
+
+    for chip8 in [&mut chip8, &mut restored] {
+        chip8.mode = Mode::Running;
+        chip8.waiting_for_key = None;
+    }
+    for cycle in 0..cycles_per_timer_tick * 2 {
+        chip8.step_cycle()?;
+        restored.step_cycle()?;
+        assert_eq!(
+            restored.count_timer_cycle(cycles_per_timer_tick),
+            chip8.count_timer_cycle(cycles_per_timer_tick),
+            "Restored timer tick after {cycle} instructions"
+        );
+    }
+    assert_eq!(restored.delay_timer, chip8.delay_timer);
+
+    Ok(())
+}
+
+/// Running until one frame passed, like `run_headless --frames 1`, executes exactly the
+/// instructions of one 60 Hz tick and decreases the delay timer once
+#[test]
+fn frame_count() {
+    let cycles_per_timer_tick = cycles_per_timer_tick();
+
+    let mut chip8 = load([jump_to_itself(chip8::PC_INIT)]);
+    chip8.delay_timer = 40;
+    chip8.sound_timer = 1;
+    assert_eq!(chip8.frame_count(), 0);
+    assert!(chip8.is_sound_playing(), "Sound before running");
+
+    let mut cycles = 0;
+    while chip8.frame_count() < 1 {
+        chip8.step_cycle().unwrap();
+        chip8.count_timer_cycle(cycles_per_timer_tick);
+        cycles += 1;
+    }
+    assert_eq!(cycles, cycles_per_timer_tick, "Instructions of one frame");
+    assert_eq!(chip8.delay_timer, 40 - 1, "Delay timer after one frame");
+    assert!(!chip8.is_sound_playing(), "Sound after one frame");
+
+    // steps without counting timer cycles, e.g. while paused, are no frames
+    chip8.step_cycle().unwrap();
+    assert_eq!(chip8.frame_count(), 1, "Frames after an uncounted step");
+}
+
+/// Every timer tick step decreases the delay timer by exactly one, also while FX0A waits for a
+/// key
+#[test]
+fn step_timer_tick() {
+    let cycles_per_timer_tick = cycles_per_timer_tick();
+
+    let mut chip8 = load([
+        Instruction::AddToRegister {
+            register: 0x0,
+            value: 1,
+        },
+        Instruction::JumpToAddress {
+            address: u16::try_from(chip8::PC_INIT).unwrap(),
+        },
+    ]);
+    chip8.delay_timer = 40;
+
+    for tick in 1..=3 {
+        let executed = chip8.step_timer_tick(cycles_per_timer_tick).unwrap();
+        assert_eq!(executed, cycles_per_timer_tick, "Instructions of a tick");
+        assert_eq!(chip8.delay_timer, 40 - tick, "Delay timer after a tick");
+    }
+
+    chip8.waiting_for_key = Some(0x1);
+    let executed = chip8.step_timer_tick(cycles_per_timer_tick).unwrap();
+    assert_eq!(executed, 0, "Instructions while waiting for a key");
+    assert_eq!(chip8.delay_timer, 40 - 4, "Delay timer while waiting");
+}
+
+/// Every pass of the frontend's loop counts once towards the timers, whether it executed an
+/// instruction or waited, so executed instructions don't run the timers twice as fast
+#[test]
+fn loop_cycles_count_once() {
+    let cycles_per_timer_tick = cycles_per_timer_tick();
+    // the 60 ticks of a second, each worth 13 instructions at 800 instructions per second
+    let second = 60 * cycles_per_timer_tick;
+
+    let mut chip8 = load([jump_to_itself(chip8::PC_INIT)]);
+    chip8.delay_timer = 200;
+    for _ in 0..second {
+        chip8.step_cycle().unwrap();
+        chip8.count_loop_cycle(Mode::Running, true, false, cycles_per_timer_tick);
+    }
+    assert_eq!(chip8.delay_timer, 200 - 60, "Delay timer after a second");
+
+    // waiting for a key executes nothing, but time passes all the same
+    chip8.waiting_for_key = Some(0x1);
+    for _ in 0..second {
+        chip8.count_loop_cycle(Mode::Running, false, false, cycles_per_timer_tick);
+    }
+    assert_eq!(chip8.delay_timer, 200 - 120, "Delay timer after waiting");
+
+    // while paused, only single steps count and only if they step the timers
+    for (executed, step_timers, expected) in [
+        (false, true, 200 - 120),
+        (true, false, 200 - 120),
+        (true, true, 200 - 121),
+    ] {
+        for _ in 0..cycles_per_timer_tick {
+            chip8.count_loop_cycle(Mode::Paused, executed, step_timers, cycles_per_timer_tick);
+        }
+        assert_eq!(
+            chip8.delay_timer, expected,
+            "Delay timer after a paused tick, executed: {executed}, stepping timers: {step_timers}"
+        );
+    }
+}
+
+/// Single steps of a loop that waits for the delay timer only get out of it when stepping
+/// advances the timers, otherwise the timer stays frozen while paused
+#[test]
+fn step_through_delay_loop() {
+    let cycles_per_timer_tick = cycles_per_timer_tick();
+    let wait_loop = u16::try_from(chip8::PC_INIT).unwrap() + 4;
+    let program = [
+        Instruction::StoreNumberInRegister {
+            number: 3,
+            register: 0x0,
+        },
+        Instruction::SetDelayTimer { register_x: 0x0 },
+        Instruction::ReadDelayTimer { register_x: 0x1 },
+        Instruction::SkipIfRegisterEqTo {
+            register: 0x1,
+            value: 0,
+        },
+        Instruction::JumpToAddress { address: wait_loop },
+        Instruction::StoreNumberInRegister {
+            number: 1,
+            register: 0x2,
+        },
+        jump_to_itself(chip8::PC_INIT + 12),
+    ];
+
+    for step_timers in [false, true] {
+        let mut chip8 = load(program);
+        chip8.mode = Mode::Paused;
+
+        let mut steps = 0;
+        while chip8.registers[0x2] == 0 && steps < 1000 {
+            chip8.step_cycle().unwrap();
+            chip8.count_loop_cycle(Mode::Paused, true, step_timers, cycles_per_timer_tick);
+            steps += 1;
+        }
+
+        if step_timers {
+            assert_eq!(chip8.delay_timer, 0, "Delay timer after the loop");
+            assert_eq!(
+                chip8.pc,
+                chip8::PC_INIT + 12,
+                "PC after stepping out of the loop"
+            );
+            assert!(
+                steps <= 3 * cycles_per_timer_tick + 5,
+                "{steps} steps to wait for 3 ticks"
+            );
+        } else {
+            assert_eq!(chip8.delay_timer, 3, "Delay timer frozen while stepping");
+            assert_eq!(chip8.registers[0x2], 0, "Loop left with frozen timers");
+        }
+    }
+}